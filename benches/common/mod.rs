@@ -0,0 +1,84 @@
+//! Generated containers shared by the benchmarks.
+
+use eof_rs::*;
+
+/// Fills a single non-returning function up to `MAX_CODE_SIZE` with copies of `block`, followed
+/// by STOP.
+fn repeated_code(block: &[u8]) -> EOFContainer {
+    let mut code = block.repeat((MAX_CODE_SIZE - 1) / block.len());
+    code.push(0x00);
+    EOFBuilder::new()
+        .add_function(0, EOF_NON_RETURNING_FUNCTION, code)
+        .build()
+        .unwrap()
+}
+
+/// The maximum number of code sections, all called from the first one.
+fn many_functions() -> EOFContainer {
+    let mut main = vec![];
+    for section in 1..1024u16 {
+        main.push(0xe3);
+        main.extend(section.to_be_bytes());
+    }
+    main.push(0x00);
+
+    let mut builder = EOFBuilder::new().add_function(0, EOF_NON_RETURNING_FUNCTION, main);
+    for _ in 1..1024 {
+        builder = builder.add_function(0, 0, hex::decode("5f505f505f505f50e4").unwrap());
+    }
+    builder.build().unwrap()
+}
+
+/// A small function followed by a data section of the largest size the header can declare.
+fn big_data() -> EOFContainer {
+    EOFBuilder::new()
+        .add_function(0, EOF_NON_RETURNING_FUNCTION, vec![0x00])
+        .set_data((0..u16::MAX).map(|i| i as u8).collect())
+        .build()
+        .unwrap()
+}
+
+/// The maximum number of subcontainers, each created by the first function and deploying
+/// runtime code of its own.
+fn subcontainers() -> EOFContainer {
+    let runtime = EOFBuilder::new()
+        .add_function(0, EOF_NON_RETURNING_FUNCTION, vec![0x00])
+        .set_data(vec![0xaa; 32])
+        .build()
+        .unwrap();
+    // PUSH0 PUSH0 RETURNCODE 0
+    let subcontainer = EOFBuilder::new()
+        .add_function(0, EOF_NON_RETURNING_FUNCTION, hex::decode("5f5fee00").unwrap())
+        .add_subcontainer(runtime)
+        .set_kind(ContainerKind::Initcode)
+        .build()
+        .unwrap();
+
+    let mut main = vec![];
+    let mut builder = EOFBuilder::new();
+    for index in 0..=u8::MAX {
+        // PUSH0 PUSH0 PUSH0 PUSH0 EOFCREATE index POP
+        main.extend([0x5f, 0x5f, 0x5f, 0x5f, 0xec, index, 0x50]);
+        builder = builder.add_subcontainer(subcontainer.clone());
+    }
+    main.push(0x00);
+    builder.add_function(0, EOF_NON_RETURNING_FUNCTION, main).build().unwrap()
+}
+
+/// Containers with the shapes that stress decoding, encoding and validation the most.
+pub fn containers() -> Vec<(&'static str, EOFContainer)> {
+    let mut rjumpv = vec![0x5f, 0xe2, 0xff];
+    rjumpv.resize(rjumpv.len() + 256 * 2, 0);
+
+    vec![
+        // PUSH1 1 PUSH1 2 ADD POP
+        ("large_code", repeated_code(&hex::decode("600160020150").unwrap())),
+        // PUSH0 RJUMPI 0, the densest sequence of merging branches
+        ("worst_case_code", repeated_code(&hex::decode("5fe10000").unwrap())),
+        // PUSH0 RJUMPV with a maximal jump table
+        ("rjumpv_tables", repeated_code(&rjumpv)),
+        ("many_functions", many_functions()),
+        ("big_data", big_data()),
+        ("subcontainers", subcontainers()),
+    ]
+}
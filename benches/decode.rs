@@ -0,0 +1,17 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use eof_rs::*;
+
+mod common;
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for (name, container) in common::containers() {
+        let code = container.to_bytes().unwrap();
+        group.throughput(Throughput::Bytes(code.len() as u64));
+        group.bench_function(name, |b| b.iter(|| from_slice(black_box(&code))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
@@ -0,0 +1,28 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use eof_rs::*;
+
+mod common;
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    for (name, container) in common::containers() {
+        let size = container.to_bytes().unwrap().len();
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(name, |b| b.iter(|| black_box(&container).to_bytes()));
+    }
+    group.finish();
+}
+
+/// The generic `to_bytes`, which goes through the serde representation of the container.
+fn encode_serde(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode_serde");
+    for (name, container) in common::containers() {
+        let size = container.to_bytes().unwrap().len();
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(name, |b| b.iter(|| to_bytes(black_box(&container))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, encode, encode_serde);
+criterion_main!(benches);
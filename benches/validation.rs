@@ -0,0 +1,15 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use eof_rs::*;
+
+mod common;
+
+fn validate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("validate");
+    for (name, container) in common::containers() {
+        group.bench_function(name, |b| b.iter(|| black_box(&container).is_valid_eof()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, validate);
+criterion_main!(benches);
@@ -1,64 +1,192 @@
-use std::io;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::path::Path;
 
 use std::fs::File;
 use std::io::BufReader;
 use eof_rs::*;
 use hex::FromHex;
 
-use clap::{arg, command, Command};
+use clap::{arg, command, Arg, Command};
 
-fn validate(input: Option<&String>) -> Result<()> {
+fn validate(input: Option<&String>, spec: Spec, kind: ContainerKind, all: bool) -> Result<()> {
     let reader: std::result::Result<EOFContainer, serde_json::Error> = if let Some(path) = input {
         serde_json::from_reader(BufReader::new(File::open(path)?))
     } else {
         serde_json::from_reader(io::stdin())
     };
-    reader?.is_valid_eof()
+    let container = reader?;
+    if !all {
+        return container.is_valid_eof_with_kind(spec, kind);
+    }
+
+    let diagnostics = container.validate_all_with_kind(spec, kind);
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+    let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    if errors > 0 {
+        return Err(Error::Message(format!("{} validation error(s)", errors)));
+    }
+    Ok(())
 }
 
-fn convert(input: Option<&String>, fmt: &str) -> Result<()> {
-    let code: Vec<u8>;
-    if input.is_some() {
-        code = Vec::from_hex(input.unwrap()).unwrap();
-    } else {
-        code = Vec::from_hex(io::read_to_string(io::stdin()).unwrap().trim()).unwrap();
+/// Reads hex encoded bytecode from the argument, or from stdin if it is omitted.
+fn read_hex(input: Option<&String>) -> Result<Vec<u8>> {
+    let input = match input {
+        Some(input) => input.clone(),
+        None => io::read_to_string(io::stdin())?,
+    };
+    Vec::from_hex(input.trim().trim_start_matches("0x"))
+        .map_err(|err| Error::Message(format!("invalid hex: {}", err)))
+}
+
+/// Reads the argument as a file if it names one, otherwise as inline input. Reads stdin if the
+/// argument is omitted.
+fn read_input(input: Option<&String>) -> Result<Vec<u8>> {
+    match input {
+        Some(input) if Path::new(input).is_file() => Ok(std::fs::read(input)?),
+        Some(input) => Ok(input.clone().into_bytes()),
+        None => {
+            let mut bytes = vec![];
+            io::stdin().read_to_end(&mut bytes)?;
+            Ok(bytes)
+        }
     }
+}
 
-    let container = eof_rs::from_slice(&code)?;
-    container.is_valid_eof()?;
+fn convert(input: Option<&String>, from: Option<Format>, to: Format, spec: Spec) -> Result<()> {
+    let input = read_input(input)?;
+    let from = from.unwrap_or_else(|| Format::detect(&input));
 
-    if fmt == "json" {
-        let json = serde_json::to_string(&container).unwrap();
-        println!("{}", json)
+    let container = from.decode(&input, spec)?;
+    container.is_valid_eof_with_spec(spec)?;
+
+    io::stdout().write_all(&to.encode(&container, spec)?)?;
+    Ok(())
+}
+
+fn disasm(input: Option<&String>, spec: Spec) -> Result<()> {
+    let container = eof_rs::from_slice_relaxed_with_spec(&read_hex(input)?, spec)?;
+    print!("{}", eof_rs::disassemble_with_spec(&container, spec));
+    Ok(())
+}
+
+fn asm(input: Option<&String>, spec: Spec) -> Result<()> {
+    let source = if let Some(path) = input {
+        std::fs::read_to_string(path)?
     } else {
-        unimplemented!();
+        io::read_to_string(io::stdin())?
+    };
+    let container = eof_rs::assemble_with_spec(&source, spec)?;
+    println!("{}", hex::encode(container.to_bytes_with_spec(spec)?));
+    Ok(())
+}
+
+fn test_fixtures(dir: &str) -> Result<()> {
+    let results = fixtures::run_fixtures_dir(Path::new(dir))?;
+
+    // Per fork: passed, failed, skipped
+    let mut summary: BTreeMap<&str, (usize, usize, usize)> = BTreeMap::new();
+    for result in &results {
+        let counts = summary.entry(&result.fork).or_default();
+        match result.outcome {
+            fixtures::Outcome::Passed => counts.0 += 1,
+            fixtures::Outcome::Failed(ref msg) => {
+                counts.1 += 1;
+                println!("FAIL {} ({}): {}", result.test, result.fork, msg);
+            }
+            fixtures::Outcome::Skipped(ref reason) => {
+                counts.2 += 1;
+                println!("SKIP {} ({}): {}", result.test, result.fork, reason);
+            }
+        }
+    }
+    for (fork, (passed, failed, skipped)) in &summary {
+        println!("{}: {} passed, {} failed, {} skipped", fork, passed, failed, skipped);
+    }
+
+    let failed: usize = summary.values().map(|counts| counts.1).sum();
+    if failed > 0 {
+        return Err(Error::Message(format!("{} fixture(s) failed", failed)));
     }
-    return Ok(());
+    Ok(())
+}
+
+fn spec_arg() -> Arg {
+    arg!(--spec <SPEC> "EOF spec revision (ShanghaiDraft, CancunDraft, Prague, Osaka)")
+        .default_value(Spec::default().name())
 }
 
-fn main() -> Result<()> {
+fn run() -> Result<()> {
     let matches = command!()
         .subcommand_required(true)
         .subcommand(
             Command::new("validate")
                 .about("validates a given EOF structure")
-                .arg(arg!([input] "Input file to operate on (stdin if omitted)")),
+                .arg(arg!([input] "Input file to operate on (stdin if omitted)"))
+                .arg(arg!(--all "Report every problem instead of stopping at the first"))
+                .arg(arg!(--initcode "Validate as initcode instead of runtime code"))
+                .arg(spec_arg()),
         )
         .subcommand(
             Command::new("convert")
                 .about("converts between various representations")
-                .arg(arg!([input] "Input file to operate on (stdin if omitted)"))
+                .arg(arg!([input] "Input file or inline hex (stdin if omitted)"))
                 .arg(
-                    arg!(--fmt <FMT> "target format (bin, json, yaml)").required(true),
-                ),
+                    arg!(--fmt <FMT> "target format (bin, hex, json, yaml)").required(true),
+                )
+                .arg(arg!(--from <FMT> "input format (detected if omitted)"))
+                .arg(spec_arg()),
+        )
+        .subcommand(
+            Command::new("disasm")
+                .about("prints a mnemonic listing of hex encoded EOF bytecode")
+                .arg(arg!([input] "Hex encoded bytecode (stdin if omitted)"))
+                .arg(spec_arg()),
+        )
+        .subcommand(
+            Command::new("asm")
+                .about("assembles a mnemonic listing into hex encoded EOF bytecode")
+                .arg(arg!([input] "Input file to operate on (stdin if omitted)"))
+                .arg(spec_arg()),
+        )
+        .subcommand(
+            Command::new("test-fixtures")
+                .about("runs ethereum/tests EOFTests fixtures against the validator")
+                .arg(arg!(<dir> "Directory containing fixture JSON files")),
         )
         .get_matches();
 
     if let Some(matches) = matches.subcommand_matches("validate") {
-        validate(matches.get_one::<String>("input"))?
+        let spec = matches.get_one::<String>("spec").expect("ensured by clap").parse()?;
+        let kind = if matches.get_flag("initcode") {
+            ContainerKind::Initcode
+        } else {
+            ContainerKind::Runtime
+        };
+        validate(matches.get_one::<String>("input"), spec, kind, matches.get_flag("all"))?
     } else if let Some(matches) = matches.subcommand_matches("convert") {
-        let fmt = matches.get_one::<String>("fmt").expect("ensurde by clap");
-        convert(matches.get_one::<String>("input"), fmt)?
+        let to = matches.get_one::<String>("fmt").expect("ensured by clap").parse()?;
+        let from = matches.get_one::<String>("from").map(|from| from.parse()).transpose()?;
+        let spec = matches.get_one::<String>("spec").expect("ensured by clap").parse()?;
+        convert(matches.get_one::<String>("input"), from, to, spec)?
+    } else if let Some(matches) = matches.subcommand_matches("disasm") {
+        let spec = matches.get_one::<String>("spec").expect("ensured by clap").parse()?;
+        disasm(matches.get_one::<String>("input"), spec)?
+    } else if let Some(matches) = matches.subcommand_matches("asm") {
+        let spec = matches.get_one::<String>("spec").expect("ensured by clap").parse()?;
+        asm(matches.get_one::<String>("input"), spec)?
+    } else if let Some(matches) = matches.subcommand_matches("test-fixtures") {
+        test_fixtures(matches.get_one::<String>("dir").expect("ensured by clap"))?
     }
     Ok(())
 }
+
+fn main() {
+    // Report errors through `Display` so validation failures show their location.
+    if let Err(error) = run() {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,12 @@
+#![no_main]
+
+use eof_rs::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for spec in Spec::ALL {
+        let _ = header_from_slice_with_spec(data, spec);
+        let _ = from_slice_with_spec(data, spec);
+        let _ = from_slice_relaxed_with_spec(data, spec);
+    }
+});
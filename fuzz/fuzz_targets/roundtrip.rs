@@ -0,0 +1,15 @@
+#![no_main]
+
+use eof_rs::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for spec in Spec::ALL {
+        if let Ok(container) = from_slice_with_spec(data, spec) {
+            // Decoding is exact, so whatever encodes must reproduce the input.
+            if let Ok(encoded) = container.to_bytes_with_spec(spec) {
+                assert_eq!(encoded, data);
+            }
+        }
+    }
+});
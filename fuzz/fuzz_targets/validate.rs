@@ -0,0 +1,16 @@
+#![no_main]
+
+use eof_rs::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for spec in Spec::ALL {
+        if let Ok(container) = from_slice_with_spec(data, spec) {
+            let valid = container.is_valid_eof_with_spec(spec);
+            let diagnostics = container.validate_all_with_spec(spec);
+            // Collecting every finding must not change the verdict.
+            let has_errors = diagnostics.iter().any(|d| d.severity == Severity::Error);
+            assert_eq!(valid.is_err(), has_errors);
+        }
+    }
+});
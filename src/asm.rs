@@ -0,0 +1,501 @@
+use std::collections::{HashMap, HashSet};
+
+use super::error::{Error, Result};
+use super::opcodes::OpCode;
+use super::spec::Spec;
+use super::types::*;
+use super::validation::max_stack_height;
+
+/// Assembles a container from a mnemonic listing, in the format produced by `disassemble`.
+///
+/// ```text
+/// .function main 0 -> nonreturning
+///     push0
+///     callf add_one
+///     rjumpi @done
+///     push1 0x2a
+/// done:
+///     stop
+/// .function add_one 1 -> 1
+///     push1 1
+///     add
+///     retf
+/// .data aabb
+/// ```
+///
+/// Comments start with `;`. Four hex digits and a colon before an instruction are the offset
+/// printed by the disassembler and are ignored, so labels of that form must be on their own line.
+/// A `.function` line may end with `max_stack_height=N` to declare the height explicitly.
+/// Jump operands are `@label` or a signed relative offset, CALLF and JUMPF operands a function
+/// name or index, other immediates a hex (`0x..`) or decimal number. `.byte` inserts raw bytes
+/// into code, `.container` opens a nested container closed by `.end`, and `.data` appends hex
+/// bytes to the data section, optionally followed by `declared=N`.
+///
+/// Functions without an explicit `max_stack_height` get the computed one, or 0 if their stack
+/// analysis fails. The result is not validated, so listings of invalid containers can be
+/// assembled too.
+pub fn assemble(source: &str) -> Result<EOFContainer> {
+    assemble_with_spec(source, Spec::default())
+}
+
+pub fn assemble_with_spec(source: &str, spec: Spec) -> Result<EOFContainer> {
+    let mut lines = source
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.split(';').next().unwrap_or_default().trim()))
+        .filter(|(_, line)| !line.is_empty());
+    parse_container(&mut lines, spec, None)
+}
+
+struct Function<'a> {
+    name: &'a str,
+    line: usize,
+    inputs: u8,
+    outputs: u8,
+    max_stack_height: Option<u16>,
+    body: Vec<(usize, Item<'a>)>,
+}
+
+enum Item<'a> {
+    Label(&'a str),
+    Offset(&'a str),
+    Instruction(OpCode, Vec<&'a str>),
+    Bytes(Vec<u8>),
+}
+
+fn syntax(line: usize, message: impl Into<String>) -> Error {
+    Error::Syntax {
+        line,
+        message: message.into(),
+    }
+}
+
+fn parse_container<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    spec: Spec,
+    opened_at: Option<usize>,
+) -> Result<EOFContainer> {
+    let mut functions: Vec<Function> = vec![];
+    let mut subcontainers = vec![];
+    let mut data = vec![];
+    let mut declared_size = None;
+
+    loop {
+        let (line, text) = match lines.next() {
+            Some(next) => next,
+            None => match opened_at {
+                Some(line) => return Err(syntax(line, ".container without .end")),
+                None => break,
+            },
+        };
+        let mut tokens = text.split_whitespace();
+        match tokens.next().unwrap_or_default() {
+            ".function" => functions.push(parse_function_header(line, tokens)?),
+            ".container" => subcontainers.push(parse_container(lines, spec, Some(line))?),
+            ".end" if opened_at.is_some() => break,
+            ".end" => return Err(syntax(line, ".end without .container")),
+            ".data" => {
+                for token in tokens {
+                    match token.strip_prefix("declared=") {
+                        Some(size) => {
+                            let size = size.parse().map_err(|_| syntax(line, "invalid size"))?;
+                            declared_size = Some(size);
+                        }
+                        None => data.extend(parse_hex(line, token)?),
+                    }
+                }
+            }
+            _ => {
+                let function = functions
+                    .last_mut()
+                    .ok_or_else(|| syntax(line, "instruction outside of a .function"))?;
+                parse_item(line, text, spec, &mut function.body)?;
+            }
+        }
+    }
+
+    let mut names = HashMap::new();
+    for (i, function) in functions.iter().enumerate() {
+        if names.insert(function.name, i).is_some() {
+            return Err(syntax(function.line, format!("duplicate function {}", function.name)));
+        }
+    }
+
+    let mut types = vec![];
+    let mut sections = vec![];
+    for function in &functions {
+        types.push(EOFTypeSectionEntry {
+            inputs: function.inputs,
+            outputs: function.outputs,
+            max_stack_height: function.max_stack_height.unwrap_or(0),
+        });
+        sections.push(EOFSection::Code(encode_function(function, &names, spec)?));
+    }
+
+    let data_size = match declared_size {
+        Some(size) => size,
+        None => u16::try_from(data.len()).map_err(|_| Error::SectionTooLarge {
+            kind: spec.data_section_kind(),
+            size: data.len(),
+            spec,
+        })?,
+    };
+    for (i, section) in sections.iter().enumerate() {
+        if let (EOFSection::Code(code), None) = (section, functions[i].max_stack_height) {
+            let computed =
+                max_stack_height(i, code, &types, data_size as usize, subcontainers.len(), spec);
+            types[i].max_stack_height = computed.unwrap_or(0);
+        }
+    }
+
+    sections.insert(0, EOFSection::Type(types));
+    sections.extend(subcontainers.into_iter().map(EOFSection::Container));
+    sections.push(EOFSection::Data(EOFData {
+        data,
+        declared_size: data_size,
+    }));
+    Ok(EOFContainer {
+        version: EOF_VERSION_1,
+        sections,
+    })
+}
+
+/// Parses `name [inputs -> outputs] [max_stack_height=N]`, defaulting to a non-returning
+/// function without inputs.
+fn parse_function_header<'a>(
+    line: usize,
+    mut tokens: impl Iterator<Item = &'a str>,
+) -> Result<Function<'a>> {
+    let name = tokens.next().ok_or_else(|| syntax(line, "missing function name"))?;
+    let mut signature: Vec<&str> = tokens.collect();
+    let height = signature.last().and_then(|token| token.strip_prefix("max_stack_height="));
+    let max_stack_height = match height {
+        Some(height) => {
+            let height = height.parse().map_err(|_| syntax(line, "invalid max stack height"))?;
+            signature.pop();
+            Some(height)
+        }
+        None => None,
+    };
+    let (inputs, outputs) = match signature[..] {
+        [] => (0, EOF_NON_RETURNING_FUNCTION),
+        [inputs, "->", outputs] => (
+            inputs.parse().map_err(|_| syntax(line, "invalid inputs"))?,
+            match outputs {
+                "nonreturning" => EOF_NON_RETURNING_FUNCTION,
+                outputs => outputs.parse().map_err(|_| syntax(line, "invalid outputs"))?,
+            },
+        ),
+        _ => return Err(syntax(line, "expected `.function name inputs -> outputs`")),
+    };
+
+    Ok(Function {
+        name,
+        line,
+        inputs,
+        outputs,
+        max_stack_height,
+        body: vec![],
+    })
+}
+
+fn parse_item<'a>(
+    line: usize,
+    text: &'a str,
+    spec: Spec,
+    body: &mut Vec<(usize, Item<'a>)>,
+) -> Result<()> {
+    let mut text = text;
+    if let Some((label, rest)) = text.split_once(':') {
+        let (label, rest) = (label.trim(), rest.trim());
+        if label.is_empty() || label.contains(char::is_whitespace) {
+            return Err(syntax(line, "invalid label"));
+        }
+        if rest.is_empty() {
+            body.push((line, Item::Label(label)));
+            return Ok(());
+        }
+        // Four hex digits before an instruction are an offset printed by the disassembler.
+        if label.len() == 4 && label.chars().all(|c| c.is_ascii_hexdigit()) {
+            body.push((line, Item::Offset(label)));
+        } else {
+            body.push((line, Item::Label(label)));
+        }
+        text = rest;
+    }
+
+    let mut tokens = text.split_whitespace();
+    let mnemonic = tokens.next().unwrap_or_default();
+    if mnemonic == ".byte" {
+        let bytes = tokens
+            .map(|token| parse_hex(line, token))
+            .collect::<Result<Vec<_>>>()?;
+        body.push((line, Item::Bytes(bytes.concat())));
+        return Ok(());
+    }
+
+    let op = OpCode::from_name(mnemonic, spec)
+        .ok_or_else(|| syntax(line, format!("unknown instruction {}", mnemonic)))?;
+    body.push((line, Item::Instruction(op, tokens.collect())));
+    Ok(())
+}
+
+fn encode_function(
+    function: &Function,
+    functions: &HashMap<&str, usize>,
+    spec: Spec,
+) -> Result<Vec<u8>> {
+    // Resolve label offsets from the instruction sizes first.
+    let mut labels = Labels::default();
+    let mut offset = 0;
+    for (line, item) in &function.body {
+        match item {
+            Item::Offset(prefix) => {
+                labels.offsets.insert(*prefix);
+            }
+            Item::Label(name) => {
+                if labels.targets.insert(*name, offset).is_some() {
+                    return Err(syntax(*line, format!("duplicate label {}", name)));
+                }
+            }
+            Item::Instruction(op, operands) if op.name == "RJUMPV" => {
+                offset += 2 + operands.len() * 2
+            }
+            Item::Instruction(op, _) => offset += 1 + op.immediates as usize,
+            Item::Bytes(bytes) => offset += bytes.len(),
+        }
+    }
+
+    let mut code = vec![];
+    for (line, item) in &function.body {
+        let (op, operands) = match item {
+            Item::Label(_) | Item::Offset(_) => continue,
+            Item::Bytes(bytes) => {
+                code.extend(bytes);
+                continue;
+            }
+            Item::Instruction(op, operands) => (op, operands),
+        };
+        let line = *line;
+        code.push(op.code);
+
+        match (op.name, &operands[..]) {
+            ("RJUMP" | "RJUMPI", [target]) => {
+                let base = code.len() + 2;
+                code.extend(jump_offset(line, target, base, &labels)?.to_be_bytes());
+            }
+            ("RJUMPV", []) => return Err(syntax(line, "RJUMPV needs at least one target")),
+            ("RJUMPV", targets) => {
+                let count = targets.len() - spec.rjumpv_max_index() as usize;
+                code.push(u8::try_from(count).map_err(|_| syntax(line, "too many targets"))?);
+                let base = code.len() + targets.len() * 2;
+                for target in targets {
+                    code.extend(jump_offset(line, target, base, &labels)?.to_be_bytes());
+                }
+            }
+            ("CALLF" | "JUMPF", [target]) => {
+                let index = match functions.get(target) {
+                    Some(index) => *index as u16,
+                    None => target
+                        .parse()
+                        .map_err(|_| syntax(line, format!("unknown function {}", target)))?,
+                };
+                code.extend(index.to_be_bytes());
+            }
+            (_, [value]) if op.immediates > 0 => {
+                code.extend(parse_immediate(line, value, op.immediates as usize)?)
+            }
+            (_, []) if op.immediates == 0 => {}
+            _ => {
+                return Err(syntax(
+                    line,
+                    format!("wrong number of operands for {}", op.name),
+                ));
+            }
+        }
+    }
+    Ok(code)
+}
+
+/// Labels of a function, and the offset prefixes that could be mistaken for labels.
+#[derive(Default)]
+struct Labels<'a> {
+    targets: HashMap<&'a str, usize>,
+    offsets: HashSet<&'a str>,
+}
+
+fn jump_offset(line: usize, target: &str, base: usize, labels: &Labels) -> Result<i16> {
+    match target.strip_prefix('@') {
+        Some(label) => {
+            let target = labels.targets.get(label).ok_or_else(|| {
+                if labels.offsets.contains(label) {
+                    syntax(line, format!("{} is an instruction offset, not a label", label))
+                } else {
+                    syntax(line, format!("unknown label {}", label))
+                }
+            })?;
+            i16::try_from(*target as isize - base as isize)
+                .map_err(|_| syntax(line, "jump target out of range"))
+        }
+        None => target
+            .trim_start_matches('+')
+            .parse()
+            .map_err(|_| syntax(line, format!("invalid jump target {}", target))),
+    }
+}
+
+/// Encodes a hex or decimal number big-endian into exactly `size` bytes.
+fn parse_immediate(line: usize, value: &str, size: usize) -> Result<Vec<u8>> {
+    let bytes = match value.strip_prefix("0x") {
+        Some(digits) => {
+            let digits = if digits.len() % 2 == 1 {
+                format!("0{}", digits)
+            } else {
+                digits.to_string()
+            };
+            parse_hex(line, &digits)?
+        }
+        None => {
+            let value: u128 = value
+                .parse()
+                .map_err(|_| syntax(line, format!("invalid number {}", value)))?;
+            value.to_be_bytes().to_vec()
+        }
+    };
+
+    let leading = bytes.len().saturating_sub(size);
+    if bytes[..leading].iter().any(|byte| *byte != 0) {
+        return Err(syntax(line, format!("{} does not fit in {} byte(s)", value, size)));
+    }
+    let mut immediate = vec![0; size.saturating_sub(bytes.len())];
+    immediate.extend(&bytes[leading..]);
+    Ok(immediate)
+}
+
+fn parse_hex(line: usize, value: &str) -> Result<Vec<u8>> {
+    hex::decode(value.trim_start_matches("0x"))
+        .map_err(|err| syntax(line, format!("invalid hex {}: {}", value, err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::de::from_slice;
+    use super::super::disasm::disassemble;
+    use super::super::ser::to_bytes;
+    use super::super::validation::EOFValidator;
+
+    #[test]
+    fn assemble_container() {
+        let container = assemble(
+            "
+            .function main 0 -> nonreturning
+                push0
+                callf add_one   ; leaves 1 on the stack
+                rjumpi @done
+                push2 0x2a
+                pop
+            done:
+                stop
+            .function add_one 1 -> 1
+                push1 1
+                add
+                retf
+            .data aabb
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(container.is_valid_eof(), Ok(()));
+        assert_eq!(
+            hex::encode(to_bytes(&container).unwrap()),
+            "ef0001010008020002000c0004040002000080000101010002\
+             5fe30001e1000461002a5000600101e4aabb"
+        );
+    }
+
+    #[test]
+    fn disassembly_roundtrip() {
+        let code = hex::decode(concat!(
+            "ef00010100080200020015000103000100000014040002000080000200000000",
+            "5fe10004611234505fe300015fe201000000015000e4",
+            "ef00010100040200010001040000000080000000",
+            "aabb",
+        ))
+        .unwrap();
+        let container = from_slice(&code).unwrap();
+
+        assert_eq!(assemble(&disassemble(&container)).unwrap(), container);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let error = |source| assemble(source).unwrap_err();
+
+        assert_eq!(error("push0"), syntax(1, "instruction outside of a .function"));
+        assert_eq!(error(".function f\n rjump @nowhere"), syntax(2, "unknown label nowhere"));
+        assert_eq!(error(".function f\n frobnicate"), syntax(2, "unknown instruction frobnicate"));
+        assert_eq!(error(".function f\n push1 0x1234"), syntax(2, "0x1234 does not fit in 1 byte(s)"));
+        assert_eq!(error(".function f\n .container\n"), syntax(2, ".container without .end"));
+        assert_eq!(
+            error(".function f\ndead: push0\n rjumpi @dead\n stop"),
+            syntax(3, "dead is an instruction offset, not a label")
+        );
+    }
+
+    #[test]
+    fn hex_like_labels() {
+        // Only four hex digits before an instruction are read as an offset
+        let container = assemble(".function f\nadd: push0\n rjumpi @add\nbeef:\n stop").unwrap();
+        assert_eq!(container.sections[1], EOFSection::Code(hex::decode("5fe1fffc00").unwrap()));
+    }
+
+    #[test]
+    fn invalid_sections() {
+        let data = format!(".function f\n stop\n.data {}", "00".repeat(0x10000));
+        assert_eq!(
+            assemble(&data),
+            Err(Error::SectionTooLarge { kind: EOF_SECTION_DATA, size: 0x10000, spec: Spec::Osaka })
+        );
+    }
+
+    #[test]
+    fn max_stack_heights() {
+        // Computed unless given, and 0 if the stack analysis fails
+        let container = assemble(
+            "
+            .function f
+                push0
+                stop
+            .function g max_stack_height=7
+                stop
+            .function h
+                add
+                stop
+            ",
+        )
+        .unwrap();
+        let heights: Vec<u16> = match &container.sections[0] {
+            EOFSection::Type(types) => types.iter().map(|t| t.max_stack_height).collect(),
+            _ => unreachable!(),
+        };
+        assert_eq!(heights, vec![1, 7, 0]);
+        assert_eq!(
+            assemble(".function f max_stack_height=x\n stop"),
+            Err(syntax(1, "invalid max stack height"))
+        );
+    }
+
+    #[test]
+    fn invalid_container_roundtrip() {
+        // ADD without enough inputs, and a declared max stack height of 2 instead of 1
+        for code in [
+            "ef00010100040200010004040000000080000160010100",
+            "ef0001010004020001000304000000008000026001fe",
+        ] {
+            let container = from_slice(&hex::decode(code).unwrap()).unwrap();
+            assert!(container.is_valid_eof().is_err());
+            assert_eq!(assemble(&disassemble(&container)).unwrap(), container);
+        }
+    }
+}
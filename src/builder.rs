@@ -0,0 +1,153 @@
+use super::error::Result;
+use super::spec::Spec;
+use super::types::*;
+use super::validation::{fix_stack_heights_with_spec, ContainerKind, EOFValidator};
+
+/// Assembles an `EOFContainer` from functions, subcontainers and data.
+///
+/// Type entries are derived from each function's inputs and outputs, and `max_stack_height` is
+/// computed with the validator's stack analysis. `build` only returns containers that pass
+/// validation.
+#[derive(Clone, Debug, Default)]
+pub struct EOFBuilder {
+    spec: Spec,
+    kind: ContainerKind,
+    functions: Vec<(u8, u8, Vec<u8>)>,
+    subcontainers: Vec<EOFContainer>,
+    data: Vec<u8>,
+}
+
+impl EOFBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds for the given spec instead of the default one.
+    pub fn with_spec(spec: Spec) -> Self {
+        Self {
+            spec,
+            ..Self::default()
+        }
+    }
+
+    /// Adds a code section. Pass `EOF_NON_RETURNING_FUNCTION` as `outputs` for functions that
+    /// never return, such as the first one.
+    pub fn add_function(mut self, inputs: u8, outputs: u8, code: Vec<u8>) -> Self {
+        self.functions.push((inputs, outputs, code));
+        self
+    }
+
+    pub fn set_data(mut self, data: Vec<u8>) -> Self {
+        self.data = data;
+        self
+    }
+
+    pub fn add_subcontainer(mut self, container: EOFContainer) -> Self {
+        self.subcontainers.push(container);
+        self
+    }
+
+    /// Validates the container as the given kind instead of runtime code.
+    pub fn set_kind(mut self, kind: ContainerKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn build(self) -> Result<EOFContainer> {
+        let types = self
+            .functions
+            .iter()
+            .map(|(inputs, outputs, _)| EOFTypeSectionEntry {
+                inputs: *inputs,
+                outputs: *outputs,
+                max_stack_height: 0,
+            })
+            .collect();
+
+        let mut sections = vec![EOFSection::Type(types)];
+        sections.extend(self.functions.into_iter().map(|(_, _, code)| EOFSection::Code(code)));
+        sections.extend(self.subcontainers.into_iter().map(EOFSection::Container));
+        sections.push(EOFSection::Data(self.data.into()));
+
+        let mut container = EOFContainer {
+            version: EOF_VERSION_1,
+            sections,
+        };
+        fix_stack_heights_with_spec(&mut container, self.spec)?;
+        container.is_valid_eof_with_kind(self.spec, self.kind)?;
+        Ok(container)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::error::Error;
+    use super::super::ser::to_bytes;
+
+    #[test]
+    fn build_container() {
+        // PUSH0 PUSH0 CALLF 1 STOP | ADD RETF
+        let container = EOFBuilder::new()
+            .add_function(0, EOF_NON_RETURNING_FUNCTION, hex::decode("5f5fe3000100").unwrap())
+            .add_function(2, 1, hex::decode("01e4").unwrap())
+            .set_data(vec![0xaa])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            hex::encode(to_bytes(&container).unwrap()),
+            "ef000101000802000200060002040001000080000202010002\
+             5f5fe300010001e4aa"
+        );
+    }
+
+    #[test]
+    fn build_subcontainer() {
+        let runtime = EOFBuilder::new()
+            .add_function(0, EOF_NON_RETURNING_FUNCTION, vec![0x00])
+            .build()
+            .unwrap();
+        // PUSH0 PUSH0 RETURNCODE 0
+        let subcontainer = EOFBuilder::new()
+            .add_function(0, EOF_NON_RETURNING_FUNCTION, hex::decode("5f5fee00").unwrap())
+            .add_subcontainer(runtime)
+            .set_kind(ContainerKind::Initcode)
+            .build()
+            .unwrap();
+        // PUSH0 PUSH0 PUSH0 PUSH0 EOFCREATE 0 POP STOP
+        let container = EOFBuilder::new()
+            .add_function(0, EOF_NON_RETURNING_FUNCTION, hex::decode("5f5f5f5fec005000").unwrap())
+            .add_subcontainer(subcontainer.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            container.sections[0],
+            EOFSection::Type(vec![EOFTypeSectionEntry {
+                inputs: 0,
+                outputs: EOF_NON_RETURNING_FUNCTION,
+                max_stack_height: 4,
+            }])
+        );
+        assert_eq!(container.sections[2], EOFSection::Container(subcontainer));
+    }
+
+    #[test]
+    fn build_invalid() {
+        // ADD without inputs
+        let result = EOFBuilder::new()
+            .add_function(0, EOF_NON_RETURNING_FUNCTION, hex::decode("0100").unwrap())
+            .build();
+        assert_eq!(
+            result.map_err(Error::into_kind).err(),
+            Some(Error::StackUnderflow { required: 2, actual: 0 })
+        );
+
+        // Missing terminating instruction
+        let result = EOFBuilder::with_spec(Spec::Prague)
+            .add_function(0, EOF_NON_RETURNING_FUNCTION, hex::decode("5f").unwrap())
+            .build();
+        assert_eq!(result.map_err(Error::into_kind).err(), Some(Error::InvalidCodeTermination));
+    }
+}
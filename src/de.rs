@@ -1,14 +1,16 @@
+use serde::de::DeserializeOwned;
+
 use super::error::{Error, Result};
+use super::spec::Spec;
 use super::types::*;
+use super::value::Value;
 
 use std::io::Read;
 
-// TODO: implement complete serde serialiser (see ciborium for an example)
-
 trait ExactReader {
     fn read_u8(&mut self) -> std::io::Result<u8>;
     fn read_u16(&mut self) -> std::io::Result<u16>;
-    fn read_bytes(&mut self, len: usize) -> std::io::Result<Vec<u8>>;
+    fn read_u32(&mut self) -> std::io::Result<u32>;
 }
 
 impl ExactReader for &[u8] {
@@ -24,202 +26,257 @@ impl ExactReader for &[u8] {
         Ok(u16::from_be_bytes(tmp))
     }
 
-    fn read_bytes(&mut self, len: usize) -> std::io::Result<Vec<u8>> {
-        let mut tmp = Vec::with_capacity(len);
-        unsafe { tmp.set_len(len) };
-        self.read_exact(&mut tmp[..])?;
-        Ok(tmp)
+    fn read_u32(&mut self) -> std::io::Result<u32> {
+        let mut tmp = [0u8; 4];
+        self.read_exact(&mut tmp)?;
+        Ok(u32::from_be_bytes(tmp))
     }
 }
 
-#[derive(Debug)]
-struct HeaderEntry {
-    kind: u8,
-    size: u16,
-}
+fn read_header(v: &[u8], spec: Spec) -> Result<EOFHeader> {
+    let mut reader = v;
 
-struct Decoder {
-    version: u8,
-    headers: Vec<HeaderEntry>,
-    contents: Vec<Vec<u8>>,
-}
+    if v.len() <= 1 {
+        return Err(Error::InvalidMagic);
+    }
 
-impl Decoder {
-    fn new() -> Self {
-        Self {
-            version: 1,
-            headers: vec![],
-            contents: vec![],
-        }
+    if (reader.read_u16()?) != EOF_MAGIC {
+        return Err(Error::InvalidMagic);
     }
 
-    fn read(&mut self, v: &[u8]) -> Result<()> {
-        let mut reader = v;
+    let version = reader.read_u8().map_err(|_| Error::UnexpectedEOF)?;
+    if version != EOF_VERSION_1 {
+        return Err(Error::UnsupportedVersion);
+    }
 
-        if v.len() <= 1 {
-            return Err(Error::InvalidMagic);
-        }
+    // Sections must appear in order: type, code, container (optional), data, terminator.
+    let mut section_kind = read_section_kind(&mut reader)?;
+    if section_kind != EOF_SECTION_TYPE {
+        return Err(Error::MissingTypeHeader);
+    }
+    let type_size = read_section_size(&mut reader)?;
+    if type_size == 0 || !type_size.is_multiple_of(4) {
+        return Err(Error::InvalidTypeSectionSize);
+    }
 
-        if (reader.read_u16()?) != EOF_MAGIC {
-            return Err(Error::InvalidMagic);
+    section_kind = read_section_kind(&mut reader)?;
+    if section_kind != EOF_SECTION_CODE {
+        return Err(Error::MissingCodeHeader);
+    }
+    let code_count = read_section_size(&mut reader)?;
+    if code_count == 0 {
+        return Err(Error::InvalidCodeSize);
+    }
+    if code_count as usize > EOF_MAX_CODE_SECTIONS {
+        return Err(Error::TooManyCodeSections);
+    }
+    let mut code_sizes = vec![];
+    for _ in 0..code_count {
+        let code_size = reader.read_u16().map_err(|_| Error::IncompleteSectionSize)?;
+        if code_size == 0 {
+            return Err(Error::InvalidCodeSize);
         }
+        code_sizes.push(code_size);
+    }
 
-        match reader.read_u8() {
-            Ok(version) => {
-                if version != EOF_VERSION_1 {
-                    return Err(Error::UnsupportedVersion);
-                }
-            }
-            Err(_) => {
-                return Err(Error::UnexpectedEOF);
+    let mut container_sizes = vec![];
+    section_kind = read_section_kind(&mut reader)?;
+    if section_kind == EOF_SECTION_CONTAINER && spec.supports_container_sections() {
+        let container_count = read_section_size(&mut reader)?;
+        if container_count == 0 {
+            return Err(Error::InvalidContainerSectionSize);
+        }
+        if container_count as usize > EOF_MAX_CONTAINER_SECTIONS {
+            return Err(Error::TooManyContainerSections);
+        }
+        for _ in 0..container_count {
+            let container_size = match spec.container_size_bytes() {
+                4 => reader.read_u32(),
+                _ => reader.read_u16().map(u32::from),
+            }.map_err(|_| Error::IncompleteSectionSize)?;
+            if container_size == 0 {
+                return Err(Error::InvalidContainerSectionSize);
             }
+            container_sizes.push(container_size);
         }
+        section_kind = read_section_kind(&mut reader)?;
+    }
 
-        let mut type_section_found = false;
-        let mut code_section_found = false;
-        let mut data_section_found = false;
-        // TODO: rewrite this to be more idiomatic
-        loop {
-            if let Ok(section_kind) = reader.read_u8() {
-                if section_kind == EOF_SECTION_TERMINATOR {
-                    break;
-                }
+    if section_kind != spec.data_section_kind() {
+        return Err(Error::MissingDataHeader);
+    }
+    let data_size = read_section_size(&mut reader)?;
 
-                if reader.len() == 0 {
-                    return Err(Error::IncompleteSections);
-                }
+    match reader.read_u8() {
+        Ok(EOF_SECTION_TERMINATOR) => {}
+        Ok(_) => return Err(Error::MissingTerminator),
+        Err(_) => return Err(Error::IncompleteSections),
+    }
 
-                if reader.len() < 2 {
-                    return Err(Error::IncompleteSectionSize);
-                }
+    Ok(EOFHeader {
+        version,
+        type_size,
+        code_sizes,
+        container_sizes,
+        data_size,
+        header_len: v.len() - reader.len(),
+    })
+}
 
-                let section_size = reader.read_u16()?;
+fn read_section_kind(reader: &mut &[u8]) -> Result<u8> {
+    reader.read_u8().map_err(|_| Error::IncompleteSections)
+}
 
-                match section_kind {
-                    EOF_SECTION_TYPE => {
-                        if section_size % 4 != 0 {
-                            return Err(Error::InvalidTypeSectionSize);
-                        }
-                        self.headers.push(HeaderEntry {
-                            kind: section_kind,
-                            size: section_size,
-                        });
-                        type_section_found = true;
-                    }
-                    EOF_SECTION_CODE => {
-                        let mut c = 0;
-                        loop {
-                            if let Ok(code_size) = reader.read_u16() {
-                                self.headers.push(HeaderEntry {
-                                    kind: section_kind,
-                                    size: code_size,
-                                });
-
-                                c += 1;
-                                if c >= section_size {
-                                    break;
-                                }
-                            } else {
-                                break;
-                            }
-                        }
-                        code_section_found = true;
+fn read_section_size(reader: &mut &[u8]) -> Result<u16> {
+    reader.read_u16().map_err(|_| Error::IncompleteSectionSize)
+}
+
+impl EOFContainerRef<'_> {
+    /// Copies the borrowed sections into an owned container, decoding type entries and
+    /// subcontainers along the way.
+    pub fn to_container(&self) -> Result<EOFContainer> {
+        self.to_container_with_spec(Spec::default())
+    }
+
+    pub fn to_container_with_spec(&self, spec: Spec) -> Result<EOFContainer> {
+        self.decode(spec, 0)
+    }
+
+    fn decode(&self, spec: Spec, depth: usize) -> Result<EOFContainer> {
+        let mut container = EOFContainer {
+            version: self.version,
+            sections: vec![],
+        };
+
+        for section in &self.sections {
+            container.sections.push(match *section {
+                EOFSectionRef::Code(code) => EOFSection::Code(code.to_vec()),
+                EOFSectionRef::Data(data) => EOFSection::Data(data.to_vec().into()),
+                EOFSectionRef::Container(bytes) => {
+                    if depth >= EOF_MAX_CONTAINER_DEPTH {
+                        return Err(Error::ContainerTooDeep);
                     }
-                    EOF_SECTION_DATA => {
-                        self.headers.push(HeaderEntry {
-                            kind: section_kind,
-                            size: section_size,
-                        });
-                        data_section_found = true;
+                    EOFSection::Container(decode_relaxed(bytes, spec, depth + 1)?)
+                }
+                EOFSectionRef::Type(mut reader) => {
+                    if !reader.len().is_multiple_of(4) {
+                        return Err(Error::InvalidTypeSectionSize);
                     }
-                    _ => {
-                        self.headers.push(HeaderEntry {
-                            kind: section_kind,
-                            size: section_size,
+
+                    let mut tmp: Vec<EOFTypeSectionEntry> = vec![];
+                    for _ in 0..(reader.len() / 4) {
+                        tmp.push(EOFTypeSectionEntry {
+                            inputs: reader.read_u8()?,
+                            outputs: reader.read_u8()?,
+                            max_stack_height: reader.read_u16()?,
                         });
                     }
+                    EOFSection::Type(tmp)
                 }
-            } else {
-                return Err(Error::IncompleteSections);
-            }
+            });
         }
 
-        for i in 0..self.headers.len() {
-            if reader.len() < self.headers[i].size as usize ||
-                (self.headers[i].size == 0 && self.headers[i].kind != EOF_SECTION_DATA)
-            {
-                return Err(Error::InvalidCodeSize);
-            }
-            self.contents.push(reader.read_bytes(
-                self.headers[i].size as usize,
-            )?);
-        }
+        Ok(container)
+    }
+}
 
-        if !type_section_found {
-            return Err(Error::MissingTypeHeader);
-        }
+pub fn from_slice(value: &[u8]) -> Result<EOFContainer> {
+    from_slice_with_spec(value, Spec::default())
+}
 
-        if !code_section_found {
-            return Err(Error::MissingCodeHeader);
-        }
+pub fn from_slice_with_spec(value: &[u8], spec: Spec) -> Result<EOFContainer> {
+    from_slice_ref_with_spec(value, spec)?.to_container_with_spec(spec)
+}
 
-        if !data_section_found {
-            return Err(Error::MissingDataHeader);
-        }
+/// Deserializes EOF bytes into any type with the serde shape of `EOFContainer`, see
+/// `to_bytes`.
+pub fn from_bytes<T: DeserializeOwned>(value: &[u8]) -> Result<T> {
+    from_bytes_with_spec(value, Spec::default())
+}
 
-        if reader.len() != 0 {
-            return Err(Error::InvalidContainerSize);
-        }
+pub fn from_bytes_with_spec<T: DeserializeOwned>(value: &[u8], spec: Spec) -> Result<T> {
+    T::deserialize(Value::from_container(from_slice_with_spec(value, spec)?))
+}
+
+/// Decodes a container whose data section may be shorter than declared, as allowed for
+/// subcontainers deployed by RETURNCODE. Nested containers are always decoded this way.
+pub fn from_slice_relaxed(value: &[u8]) -> Result<EOFContainer> {
+    from_slice_relaxed_with_spec(value, Spec::default())
+}
+
+pub fn from_slice_relaxed_with_spec(value: &[u8], spec: Spec) -> Result<EOFContainer> {
+    decode_relaxed(value, spec, 0)
+}
 
-        Ok(())
+fn decode_relaxed(value: &[u8], spec: Spec, depth: usize) -> Result<EOFContainer> {
+    let (header, container) = read_sections(value, spec, true)?;
+    let mut container = container.decode(spec, depth)?;
+    for section in &mut container.sections {
+        if let EOFSection::Data(data) = section {
+            data.declared_size = header.data_size;
+        }
     }
+    Ok(container)
+}
 
-    fn finalize(self) -> Result<EOFContainer> {
-        let mut container = EOFContainer {
-            version: self.version,
-            sections: vec![],
-        };
+/// Decodes the header of `value` and returns sections borrowing from it, without copying any
+/// section bodies. Subcontainers are not decoded.
+pub fn from_slice_ref(value: &[u8]) -> Result<EOFContainerRef<'_>> {
+    from_slice_ref_with_spec(value, Spec::default())
+}
 
-        // TODO: make this idiomatic
-        for i in 0..self.headers.len() {
-            let kind = self.headers[i].kind;
-            if kind == EOF_SECTION_CODE {
-                container.sections.push(EOFSection::Code(
-                    self.contents[i].to_vec(),
-                ));
-            } else if kind == EOF_SECTION_DATA {
-                container.sections.push(EOFSection::Data(
-                    self.contents[i].to_vec(),
-                ));
-            } else if kind == EOF_SECTION_TYPE {
-                let mut reader = &self.contents[i][..];
-
-                if reader.len() % 4 != 0 {
-                    return Err(Error::InvalidTypeSectionSize);
-                }
+pub fn from_slice_ref_with_spec(value: &[u8], spec: Spec) -> Result<EOFContainerRef<'_>> {
+    Ok(read_sections(value, spec, false)?.1)
+}
 
-                let mut tmp: Vec<EOFTypeSectionEntry> = vec![];
-                for _ in 0..(reader.len() / 4) {
-                    tmp.push(EOFTypeSectionEntry {
-                        inputs: reader.read_u8()?,
-                        outputs: reader.read_u8()?,
-                        max_stack_height: reader.read_u16()?,
-                    });
-                }
-                container.sections.push(EOFSection::Type(tmp));
-            } else {
-                return Err(Error::UnsupportedSectionKind);
-            }
+fn read_sections(
+    value: &[u8],
+    spec: Spec,
+    relaxed: bool,
+) -> Result<(EOFHeader, EOFContainerRef<'_>)> {
+    let header = read_header(value, spec)?;
+
+    let mut reader = &value[header.header_len..];
+    let mut sections = vec![];
+    // Only the data section may be cut short, and only in relaxed mode.
+    let mut take = |size: usize, truncate: bool| {
+        let size = if truncate { size.min(reader.len()) } else { size };
+        if reader.len() < size {
+            return Err(Error::InvalidCodeSize);
         }
+        let (content, rest) = reader.split_at(size);
+        reader = rest;
+        Ok(content)
+    };
+
+    sections.push(EOFSectionRef::Type(take(header.type_size as usize, false)?));
+    for size in &header.code_sizes {
+        sections.push(EOFSectionRef::Code(take(*size as usize, false)?));
+    }
+    for size in &header.container_sizes {
+        sections.push(EOFSectionRef::Container(take(*size as usize, false)?));
+    }
+    sections.push(EOFSectionRef::Data(take(header.data_size as usize, relaxed)?));
 
-        Ok(container)
+    if !reader.is_empty() {
+        return Err(Error::InvalidContainerSize);
     }
+
+    let container = EOFContainerRef {
+        version: header.version,
+        sections,
+    };
+    Ok((header, container))
 }
 
-pub fn from_slice(value: &[u8]) -> Result<EOFContainer> {
-    let mut decoder = Decoder::new();
-    decoder.read(value)?;
-    decoder.finalize()
+/// Decodes only the header of `value`. Section bodies are neither read nor required to be
+/// present, so the declared sizes can be checked against the input separately.
+pub fn header_from_slice(value: &[u8]) -> Result<EOFHeader> {
+    header_from_slice_with_spec(value, Spec::default())
+}
+
+pub fn header_from_slice_with_spec(value: &[u8], spec: Spec) -> Result<EOFHeader> {
+    read_header(value, spec)
 }
 
 #[cfg(test)]
@@ -231,7 +288,7 @@ mod tests {
     #[test]
     fn decode_eof_bytes() {
         let input = hex::decode(
-            "ef000101000802000200010001030005000000000001010001fefe0001020304",
+            "ef000101000802000200010001040005000000000001010001fefe0001020304",
         ).unwrap();
         let container = EOFContainer {
             version: 1,
@@ -250,7 +307,7 @@ mod tests {
                 ]),
                 EOFSection::Code(vec![0xfe]),
                 EOFSection::Code(vec![0xfe]),
-                EOFSection::Data(vec![0, 1, 2, 3, 4]),
+                EOFSection::Data(vec![0, 1, 2, 3, 4].into()),
             ],
         };
 
@@ -258,6 +315,169 @@ mod tests {
         assert_eq!(deserialized, container);
     }
 
+    #[test]
+    fn decode_with_spec() {
+        // Draft specs use 0x03 for the data section
+        let input = hex::decode(
+            "ef000101000802000200010001030005000000000001010001fefe0001020304",
+        ).unwrap();
+        let deserialized = from_slice_with_spec(&input[..], Spec::ShanghaiDraft).unwrap();
+        assert_eq!(
+            deserialized.sections.last(),
+            Some(&EOFSection::Data(vec![0, 1, 2, 3, 4].into()))
+        );
+        assert_eq!(from_slice(&input[..]), Err(Error::InvalidContainerSectionSize));
+
+        // Prague uses 2-byte container sizes
+        let input = hex::decode(
+            "ef0001010004020001000103000100140400000000000000feef000101000402000100010400000000000000fe",
+        ).unwrap();
+        let deserialized = from_slice_with_spec(&input[..], Spec::Prague).unwrap();
+        assert_eq!(deserialized.sections.len(), 4);
+    }
+
+    #[test]
+    fn decode_header() {
+        let input = hex::decode(
+            "ef00010100080200020001000303000100000014040002000000800000000000fee4e4e4ef000101000402000100010400000000800000fe0102",
+        ).unwrap();
+        let header = header_from_slice(&input[..]).unwrap();
+
+        assert_eq!(
+            header,
+            EOFHeader {
+                version: 1,
+                type_size: 8,
+                code_sizes: vec![1, 3],
+                container_sizes: vec![20],
+                data_size: 2,
+                header_len: 24,
+            }
+        );
+        assert_eq!(header.type_offset(), 24);
+        assert_eq!(header.code_offsets(), vec![32, 33]);
+        assert_eq!(header.container_offsets(), vec![36]);
+        assert_eq!(header.data_offset(), 56);
+        assert_eq!(header.container_size(), input.len());
+        assert_eq!(&input[header.code_offsets()[1]..header.container_offsets()[0]], [0xe4; 3]);
+
+        // Bodies are not needed to decode the header
+        let header_len = header.header_len;
+        assert_eq!(header_from_slice(&input[..header_len]), Ok(header));
+        assert_eq!(from_slice(&input[..header_len]), Err(Error::InvalidCodeSize));
+    }
+
+    #[test]
+    fn decode_truncated_data() {
+        // Data section declares 4 bytes but only 2 are present
+        let input = hex::decode("ef00010100040200010001040004000080000000aabb").unwrap();
+        assert_eq!(from_slice(&input[..]), Err(Error::InvalidCodeSize));
+
+        let container = from_slice_relaxed(&input[..]).unwrap();
+        assert_eq!(
+            container.sections[2],
+            EOFSection::Data(EOFData {
+                data: vec![0xaa, 0xbb],
+                declared_size: 4,
+            })
+        );
+
+        // Subcontainers are decoded in relaxed mode
+        let input = hex::decode(
+            "ef000101000402000100040300010000001604000000008000025f5fee00ef00010100040200010001040004000080000000aabb",
+        ).unwrap();
+        let container = from_slice(&input[..]).unwrap();
+        match container.sections[2] {
+            EOFSection::Container(ref subcontainer) => {
+                assert_eq!(subcontainer.sections[2], EOFSection::Data(EOFData {
+                    data: vec![0xaa, 0xbb],
+                    declared_size: 4,
+                }));
+            }
+            _ => panic!("expected a container section"),
+        }
+    }
+
+    #[test]
+    fn decode_borrowed() {
+        let input = hex::decode(
+            "ef00010100040200010001030001000000140400020000000000feef000101000402000100010400000000000000fe0102",
+        ).unwrap();
+        let container = from_slice_ref(&input[..]).unwrap();
+
+        assert_eq!(container.version, 1);
+        assert_eq!(
+            container.sections,
+            vec![
+                EOFSectionRef::Type(&input[22..26]),
+                EOFSectionRef::Code(&input[26..27]),
+                EOFSectionRef::Container(&input[27..47]),
+                EOFSectionRef::Data(&input[47..49]),
+            ]
+        );
+        // Sections point into the input instead of being copied.
+        assert_eq!(container.sections[1].bytes().as_ptr(), input[26..].as_ptr());
+        assert_eq!(container.to_container(), from_slice(&input[..]));
+
+        // Subcontainers are only decoded on conversion.
+        let mut input = input;
+        input[27] = 0;
+        let container = from_slice_ref(&input[..]).unwrap();
+        assert_eq!(container.to_container(), Err(Error::InvalidMagic));
+    }
+
+    #[test]
+    fn decode_nested_container() {
+        let input = hex::decode(
+            "ef00010100040200010001030001000000140400000000000000feef000101000402000100010400000000000000fe",
+        ).unwrap();
+        let subcontainer = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![
+                    EOFTypeSectionEntry {
+                        inputs: 0,
+                        outputs: 0,
+                        max_stack_height: 0,
+                    },
+                ]),
+                EOFSection::Code(vec![0xfe]),
+                EOFSection::Data(vec![].into()),
+            ],
+        };
+        let container = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![
+                    EOFTypeSectionEntry {
+                        inputs: 0,
+                        outputs: 0,
+                        max_stack_height: 0,
+                    },
+                ]),
+                EOFSection::Code(vec![0xfe]),
+                EOFSection::Container(subcontainer),
+                EOFSection::Data(vec![].into()),
+            ],
+        };
+
+        let deserialized = from_slice(&input[..]).unwrap();
+        assert_eq!(deserialized, container);
+    }
+
+    #[test]
+    fn invalid_container_section_size() {
+        // Zero container sections
+        let input = hex::decode("ef0001010004020001000103000004000000000000fe").unwrap();
+        let deserialized = from_slice(&input[..]);
+        assert_eq!(deserialized, Err(Error::InvalidContainerSectionSize));
+
+        // Zero sized container
+        let input = hex::decode("ef000101000402000100010300010000000004000000000000fe").unwrap();
+        let deserialized = from_slice(&input[..]);
+        assert_eq!(deserialized, Err(Error::InvalidContainerSectionSize));
+    }
+
     #[test]
     fn unexpected_eof() {
         let input = hex::decode("ef00").unwrap();
@@ -274,35 +494,35 @@ mod tests {
 
     #[test]
     fn invalid_version() {
-        let input = hex::decode("ef0002010004020001002903000000000000027fef000101000402000100010300000000000000fe00000000000000000000000060005260146000f3").unwrap();
+        let input = hex::decode("ef0002010004020001002904000000000000027fef000101000402000100010300000000000000fe00000000000000000000000060005260146000f3").unwrap();
         let deserialized = from_slice(&input[..]);
         assert_eq!(deserialized, Err(Error::UnsupportedVersion));
     }
 
     #[test]
     fn invalid_type_section_size() {
-        let input = hex::decode("ef000101000202000100010300000000000000fe").unwrap();
+        let input = hex::decode("ef000101000202000100010400000000000000fe").unwrap();
         let deserialized = from_slice(&input[..]);
         assert_eq!(deserialized, Err(Error::InvalidTypeSectionSize));
     }
 
     #[test]
     fn invalid_container_size() {
-        let input = hex::decode("ef000101000402000100010300000000000000feaabbcc").unwrap();
+        let input = hex::decode("ef000101000402000100010400000000000000feaabbcc").unwrap();
         let deserialized = from_slice(&input[..]);
         assert_eq!(deserialized, Err(Error::InvalidContainerSize));
     }
 
     #[test]
     fn missing_type_header() {
-        let input = hex::decode("ef0001020001000103000200feaabb").unwrap();
+        let input = hex::decode("ef0001020001000104000200feaabb").unwrap();
         let deserialized = from_slice(&input[..]);
         assert_eq!(deserialized, Err(Error::MissingTypeHeader));
     }
 
     #[test]
     fn missing_code_header() {
-        let input = hex::decode("ef00010100040300020000000000feaabb").unwrap();
+        let input = hex::decode("ef00010100040400020000000000feaabb").unwrap();
         let deserialized = from_slice(&input[..]);
 
         assert_eq!(deserialized, Err(Error::MissingCodeHeader));
@@ -314,4 +534,17 @@ mod tests {
         let deserialized = from_slice(&input[..]);
         assert_eq!(deserialized, Err(Error::MissingDataHeader));
     }
+
+    #[test]
+    fn deserialize_bytes() {
+        let input = hex::decode(
+            "ef00010100040200010001030001000000140400000000000000feef000101000402000100010400000000000000fe",
+        )
+        .unwrap();
+
+        let container: EOFContainer = from_bytes(&input).unwrap();
+        assert_eq!(container, from_slice(&input).unwrap());
+        assert_eq!(super::super::ser::to_bytes(&container).unwrap(), input);
+        assert_eq!(from_bytes::<EOFContainer>(&input[..10]), Err(Error::IncompleteSectionSize));
+    }
 }
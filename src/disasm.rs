@@ -0,0 +1,232 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use super::opcodes::OpCode;
+use super::spec::Spec;
+use super::types::*;
+use super::validation::rjumpv_count;
+
+/// Renders a container as a mnemonic listing.
+///
+/// Every code section becomes a `.function` block with its declared `max_stack_height` and one
+/// `offset: MNEMONIC immediate` line per instruction. Relative jumps are shown as `@Lxxxx` labels
+/// at their absolute target, CALLF and JUMPF targets as `fn_N`, subcontainers as nested
+/// `.container` blocks and the data section as `.data`, with `declared=N` if it is truncated.
+/// Bytes that do not decode to an instruction are kept as `.byte` lines.
+pub fn disassemble(container: &EOFContainer) -> String {
+    disassemble_with_spec(container, Spec::default())
+}
+
+pub fn disassemble_with_spec(container: &EOFContainer, spec: Spec) -> String {
+    let mut listing = String::new();
+    write_container(&mut listing, container, spec, "");
+    listing
+}
+
+/// Renders a single code section, one instruction per line.
+pub fn disassemble_code(code: &[u8], spec: Spec) -> String {
+    let mut listing = String::new();
+    write_code(&mut listing, code, spec, "");
+    listing
+}
+
+fn write_container(out: &mut String, container: &EOFContainer, spec: Spec, indent: &str) {
+    let types: &[EOFTypeSectionEntry] = container
+        .sections
+        .iter()
+        .find_map(|section| match section {
+            EOFSection::Type(types) => Some(types.as_slice()),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let mut function = 0;
+    for section in &container.sections {
+        match section {
+            EOFSection::Type(_) => {}
+            EOFSection::Code(code) => {
+                write!(out, "{}.function fn_{}", indent, function).unwrap();
+                if let Some(entry) = types.get(function) {
+                    let outputs = match entry.outputs {
+                        EOF_NON_RETURNING_FUNCTION => "nonreturning".to_string(),
+                        outputs => outputs.to_string(),
+                    };
+                    write!(
+                        out,
+                        " {} -> {} max_stack_height={}",
+                        entry.inputs, outputs, entry.max_stack_height
+                    )
+                    .unwrap();
+                }
+                out.push('\n');
+                write_code(out, code, spec, indent);
+                function += 1;
+            }
+            EOFSection::Container(subcontainer) => {
+                writeln!(out, "{}.container", indent).unwrap();
+                write_container(out, subcontainer, spec, &format!("{}  ", indent));
+                writeln!(out, "{}.end", indent).unwrap();
+            }
+            EOFSection::Data(data) => {
+                write!(out, "{}.data", indent).unwrap();
+                if !data.data.is_empty() {
+                    write!(out, " {}", hex::encode(&data.data)).unwrap();
+                }
+                if data.is_truncated() {
+                    write!(out, " declared={}", data.declared_size).unwrap();
+                }
+                out.push('\n');
+            }
+        }
+    }
+}
+
+fn write_code(out: &mut String, code: &[u8], spec: Spec, indent: &str) {
+    // Decode instructions first so that jump targets can be labelled before they are printed.
+    let mut instructions: Vec<(usize, Option<OpCode>, usize)> = vec![];
+    let mut i = 0;
+    while i < code.len() {
+        let op = match OpCode::from(code[i], spec) {
+            Ok(op) => op,
+            Err(_) => {
+                instructions.push((i, None, 1));
+                i += 1;
+                continue;
+            }
+        };
+        let mut size = 1 + op.immediates as usize;
+        if op.name == "RJUMPV" && i + 1 < code.len() {
+            size += rjumpv_count(code[i + 1], spec) * 2;
+        }
+        if i + size > code.len() {
+            instructions.push((i, None, code.len() - i));
+            break;
+        }
+        instructions.push((i, Some(op), size));
+        i += size;
+    }
+
+    let starts: BTreeSet<usize> = instructions
+        .iter()
+        .filter(|(_, op, _)| op.is_some())
+        .map(|(offset, _, _)| *offset)
+        .collect();
+    let mut labels = BTreeSet::new();
+    for (offset, op, size) in &instructions {
+        if let Some(op) = op {
+            for target in jump_offsets(code, *offset, op, *size, spec) {
+                let target = (*offset + size) as isize + target as isize;
+                if target >= 0 && starts.contains(&(target as usize)) {
+                    labels.insert(target as usize);
+                }
+            }
+        }
+    }
+
+    for (offset, op, size) in instructions {
+        if labels.contains(&offset) {
+            writeln!(out, "{}L{:04x}:", indent, offset).unwrap();
+        }
+        write!(out, "{}  {:04x}: ", indent, offset).unwrap();
+
+        let op = match op {
+            Some(op) => op,
+            None => {
+                writeln!(out, ".byte 0x{}", hex::encode(&code[offset..offset + size])).unwrap();
+                continue;
+            }
+        };
+        out.push_str(op.name);
+
+        let immediate = &code[offset + 1..offset + size];
+        let base = offset + size;
+        let jumps = jump_offsets(code, offset, &op, size, spec);
+        match op.name {
+            "RJUMP" | "RJUMPI" | "RJUMPV" => {
+                for relative in jumps {
+                    let target = base as isize + relative as isize;
+                    if target >= 0 && labels.contains(&(target as usize)) {
+                        write!(out, " @L{:04x}", target).unwrap();
+                    } else {
+                        write!(out, " {:+}", relative).unwrap();
+                    }
+                }
+            }
+            "CALLF" | "JUMPF" => {
+                write!(out, " fn_{}", u16::from_be_bytes([immediate[0], immediate[1]])).unwrap();
+            }
+            _ if !immediate.is_empty() => write!(out, " 0x{}", hex::encode(immediate)).unwrap(),
+            _ => {}
+        }
+        out.push('\n');
+    }
+}
+
+/// Relative offsets of the jumps encoded in a RJUMP, RJUMPI or RJUMPV instruction.
+fn jump_offsets(code: &[u8], offset: usize, op: &OpCode, size: usize, spec: Spec) -> Vec<i16> {
+    let table = match op.name {
+        "RJUMP" | "RJUMPI" => offset + 1..offset + 3,
+        "RJUMPV" => offset + 2..offset + 2 + rjumpv_count(code[offset + 1], spec) * 2,
+        _ => return vec![],
+    };
+    debug_assert!(table.end <= offset + size);
+    code[table]
+        .chunks(2)
+        .map(|bytes| i16::from_be_bytes([bytes[0], bytes[1]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::de::from_slice;
+
+    #[test]
+    fn disassemble_container() {
+        // fn_0: PUSH0 RJUMPI +4 PUSH2 0x1234 POP POP CALLF 1 PUSH0 RJUMPV [-11, 0] STOP
+        // fn_1: RETF
+        let code = hex::decode(concat!(
+            "ef00010100080200020014000103000100000014040002000080000200000000",
+            "5fe100046112345050e300015fe201fff5000000e4",
+            "ef00010100040200010001040000000080000000",
+            "aabb",
+        ))
+        .unwrap();
+        let container = from_slice(&code).unwrap();
+
+        assert_eq!(
+            disassemble(&container),
+            "\
+.function fn_0 0 -> nonreturning max_stack_height=2
+  0000: PUSH0
+  0001: RJUMPI @L0008
+  0004: PUSH2 0x1234
+  0007: POP
+L0008:
+  0008: POP
+  0009: CALLF fn_1
+  000c: PUSH0
+  000d: RJUMPV @L0008 @L0013
+L0013:
+  0013: STOP
+.function fn_1 0 -> 0 max_stack_height=0
+  0000: RETF
+.container
+  .function fn_0 0 -> nonreturning max_stack_height=0
+    0000: STOP
+  .data
+.end
+.data aabb
+"
+        );
+    }
+
+    #[test]
+    fn disassemble_invalid_code() {
+        // Undefined opcode, a jump into an immediate and a truncated PUSH2
+        assert_eq!(
+            disassemble_code(&hex::decode("0ce0fffe61ff").unwrap(), Spec::Osaka),
+            "  0000: .byte 0x0c\n  0001: RJUMP -2\n  0004: .byte 0x61ff\n"
+        );
+    }
+}
@@ -30,10 +30,11 @@ impl fmt::Display for EOFContainer {
                 EOFSection::Data(ref data) => {
                     writeln!(
                         f,
-                        "| {} | Data | {} | {} |",
+                        "| {} | Data | {}/{} | {} |",
                         i,
-                        data.len(),
-                        hex::encode(data)
+                        data.data.len(),
+                        data.declared_size,
+                        hex::encode(&data.data)
                     )?
                 }
                 EOFSection::Type(ref types) => {
@@ -48,6 +49,15 @@ impl fmt::Display for EOFContainer {
 
                     writeln!(f, "| {} | Type | {} | {} |", i, types.len() * 2, type_str)?
                 }
+                EOFSection::Container(ref container) => {
+                    writeln!(
+                        f,
+                        "| {} | Container | {} | EOF Version {} |",
+                        i,
+                        container.sections.len(),
+                        container.version
+                    )?
+                }
             }
         }
 
@@ -82,7 +92,7 @@ mod tests {
                 ]),
                 EOFSection::Code(vec![0xfe]),
                 EOFSection::Code(vec![0xfe]),
-                EOFSection::Data(vec![0, 1, 2, 3, 4]),
+                EOFSection::Data(vec![0, 1, 2, 3, 4].into()),
             ],
         };
         println!("{}", container);
@@ -93,7 +103,7 @@ mod tests {
 
     #[test]
     fn andreis_code() {
-        let bin = "ef000101000c020003003b0017001d0300000000000004010100030101000460043560003560e01c63c766526781145d001c63c6c2ea1781145d00065050600080fd50b0000260005260206000f350b0000160005260206000f3600181115d0004506001b160018103b0000181029050b1600281115d0004506001b160028103b0000260018203b00002019050b1";
+        let bin = "ef000101000c020003003b0017001d0400000000000004010100030101000460043560003560e01c63c76652678114e1001c63c6c2ea178114e100065050600080fd50e3000260005260206000f350e3000160005260206000f360018111e10004506001e460018103e3000181029050e460028111e10004506001e460028103e3000260018203e30002019050e4";
         let input = hex::decode(bin).unwrap();
         let deserialized = from_slice(&input[..]).unwrap();
         let formatted = format!("{}", deserialized);
@@ -1,8 +1,21 @@
 use serde::{de, ser};
 use std::fmt::{self, Display};
 
+use super::spec::Spec;
+
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Position inside a container at which a validation error was detected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    /// Index of the code section (and its type entry).
+    pub section: usize,
+    /// Byte offset within the code section, if the error concerns a single position.
+    pub offset: Option<usize>,
+    /// Opcode found at `offset`, if the offset points at an instruction.
+    pub opcode: Option<u8>,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Error {
     Message(String),
@@ -25,12 +38,12 @@ pub enum Error {
     TruncatedImmediate,
     InvalidSectionArgument,
     InvalidJumpdest,
-    ConflictingStack,
+    ConflictingStack { expected: u16, actual: u16 },
     InvalidBranchCount,
-    StackUnderflow,
+    StackUnderflow { required: u16, actual: u16 },
     StackOverflow,
-    InvalidOutputs,
-    InvalidMaxStackHeight,
+    InvalidOutputs { expected: u16, actual: u16 },
+    InvalidMaxStackHeight { declared: u16, computed: u16 },
     InvalidCodeTermination,
     UnreachableCode,
     UnsupportedSectionKind,
@@ -40,7 +53,59 @@ pub enum Error {
     InvalidSectionOrder,
     MismatchingCodeAndTypeSections,
     DuplicateTypeSection,
-    InvalidStackHeight,
+    DuplicateDataSection,
+    InvalidStackHeight { expected: u16, actual: u16 },
+    InvalidContainerSectionSize,
+    TooManyCodeSections,
+    TooManyContainerSections,
+    InvalidDataloadnIndex,
+    InvalidContainerSectionIndex,
+    InvalidNonReturningFlag,
+    CallfToNonReturningFunction,
+    JumpfDestinationIncompatibleOutputs,
+    UnreachableCodeSection,
+    TruncatedDataSection,
+    InvalidDataSize,
+    /// `kind` is the header kind of the section under `spec`.
+    SectionTooLarge { kind: u8, size: usize, spec: Spec },
+    ContainerTooLarge { size: usize, limit: usize },
+    ContainerTooDeep,
+    OrphanSubcontainer { index: usize },
+    AmbiguousContainerKind { index: usize },
+    IncompatibleContainerKind,
+    Syntax { line: usize, message: String },
+    Located(Location, Box<Error>),
+}
+
+impl Error {
+    /// Attaches the location the error was detected at.
+    pub fn at(self, section: usize, offset: Option<usize>, opcode: Option<u8>) -> Self {
+        Error::Located(Location { section, offset, opcode }, Box::new(self))
+    }
+
+    /// Location of the error, if known.
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Error::Located(location, _) => Some(location),
+            _ => None,
+        }
+    }
+
+    /// The error with its location stripped.
+    pub fn kind(&self) -> &Error {
+        match self {
+            Error::Located(_, error) => error.kind(),
+            error => error,
+        }
+    }
+
+    /// Consumes the error, returning it with its location stripped.
+    pub fn into_kind(self) -> Error {
+        match self {
+            Error::Located(_, error) => error.into_kind(),
+            error => error,
+        }
+    }
 }
 
 impl ser::Error for Error {
@@ -80,12 +145,22 @@ impl Display for Error {
             TruncatedImmediate => write!(f, "Truncated immediate"),
             InvalidSectionArgument => write!(f, "Invalid section argument"),
             InvalidJumpdest => write!(f, "Invalid jumpdest"),
-            ConflictingStack => write!(f, "Conflicting stack"),
+            ConflictingStack { expected, actual } => {
+                write!(f, "Conflicting stack (expected {}, got {})", expected, actual)
+            }
             InvalidBranchCount => write!(f, "Invalid branch count"),
-            StackUnderflow => write!(f, "Stack underflow"),
+            StackUnderflow { required, actual } => {
+                write!(f, "Stack underflow (required {}, got {})", required, actual)
+            }
             StackOverflow => write!(f, "Stack overflow"),
-            InvalidOutputs => write!(f, "Invalid outputs"),
-            InvalidMaxStackHeight => write!(f, "Invalid max stack height"),
+            InvalidOutputs { expected, actual } => {
+                write!(f, "Invalid outputs (expected {}, got {})", expected, actual)
+            }
+            InvalidMaxStackHeight { declared, computed } => write!(
+                f,
+                "Invalid max stack height (declared {}, computed {})",
+                declared, computed
+            ),
             InvalidCodeTermination => write!(f, "Invalid code termination"),
             UnreachableCode => write!(f, "Unreachable code"),
             InvalidTypeSectionSize => write!(f, "Invalid Type section size"),
@@ -97,9 +172,55 @@ impl Display for Error {
                 write!(f, "Mismatching number of Code and Type sections")
             }
             DuplicateTypeSection => write!(f, "Duplicate Type section"),
-            InvalidStackHeight => write!(f, "Invalid stack height"),
+            DuplicateDataSection => write!(f, "Duplicate Data section"),
+            InvalidStackHeight { expected, actual } => {
+                write!(f, "Invalid stack height (expected {}, got {})", expected, actual)
+            }
+            InvalidContainerSectionSize => write!(f, "Invalid Container section size"),
+            TooManyCodeSections => write!(f, "Too many Code sections"),
+            TooManyContainerSections => write!(f, "Too many Container sections"),
+            InvalidDataloadnIndex => write!(f, "Invalid DATALOADN index"),
+            InvalidContainerSectionIndex => write!(f, "Invalid Container section index"),
+            InvalidNonReturningFlag => write!(f, "Invalid non-returning flag"),
+            CallfToNonReturningFunction => write!(f, "CALLF to non-returning function"),
+            JumpfDestinationIncompatibleOutputs => {
+                write!(f, "JUMPF destination has incompatible outputs")
+            }
+            UnreachableCodeSection => write!(f, "Unreachable code section"),
+            TruncatedDataSection => write!(f, "Data section is shorter than declared"),
+            InvalidDataSize => write!(f, "Invalid Data section size"),
+            SectionTooLarge { kind, size, spec } => {
+                write!(f, "{} section too large ({} bytes)", spec.section_name(*kind), size)
+            }
+            ContainerTooLarge { size, limit } => {
+                write!(f, "Container too large ({} bytes, limit {})", size, limit)
+            }
+            ContainerTooDeep => write!(f, "Subcontainers nested too deeply"),
+            OrphanSubcontainer { index } => write!(f, "Subcontainer {} is never referenced", index),
+            AmbiguousContainerKind { index } => write!(
+                f,
+                "Subcontainer {} is referenced by both EOFCREATE and RETURNCODE",
+                index
+            ),
+            IncompatibleContainerKind => {
+                write!(f, "Instruction not allowed in this kind of container")
+            }
+            Syntax { line, message } => write!(f, "Syntax error at line {}: {}", line, message),
+            Located(location, error) => write!(f, "{} at {}", error, location),
+        }
+    }
+}
 
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "code section {}", self.section)?;
+        if let Some(offset) = self.offset {
+            write!(f, ", offset {}", offset)?;
+        }
+        if let Some(opcode) = self.opcode {
+            write!(f, " (opcode 0x{:02x})", opcode)?;
         }
+        Ok(())
     }
 }
 
@@ -116,3 +237,9 @@ impl From<serde_json::Error> for Error {
         Error::Message(error.to_string())
     }
 }
+
+impl From<serde_yaml::Error> for Error {
+    fn from(error: serde_yaml::Error) -> Self {
+        Error::Message(error.to_string())
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::de::from_slice_with_spec;
+use super::error::{Error, Result};
+use super::spec::Spec;
+use super::validation::{ContainerKind, EOFValidator};
+
+/// A file of `EOFTests` fixtures from ethereum/tests, keyed by test name.
+pub type FixtureFile = BTreeMap<String, Fixture>;
+
+#[derive(Deserialize, Debug)]
+pub struct Fixture {
+    pub vectors: BTreeMap<String, FixtureVector>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FixtureVector {
+    pub code: String,
+    /// `INITCODE` or `RUNTIME`, the latter being the default.
+    #[serde(rename = "containerKind", default)]
+    pub container_kind: Option<String>,
+    pub results: BTreeMap<String, ForkExpectation>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ForkExpectation {
+    pub result: bool,
+    pub exception: Option<String>,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum Outcome {
+    Passed,
+    Failed(String),
+    /// The vector could not be fully checked: the fork has no matching `Spec`, or the code was
+    /// rejected as expected but the exception has no known mapping.
+    Skipped(String),
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct FixtureResult {
+    /// Test name and vector key, e.g. `EOF1_valid_rjump/0`.
+    pub test: String,
+    pub fork: String,
+    pub outcome: Outcome,
+}
+
+/// Runs every vector of a fixture file against each fork it has expectations for.
+pub fn run_fixtures(json: &str) -> Result<Vec<FixtureResult>> {
+    let file: FixtureFile = serde_json::from_str(json)?;
+
+    let mut results = vec![];
+    for (name, fixture) in &file {
+        for (key, vector) in &fixture.vectors {
+            for (fork, expectation) in &vector.results {
+                let kind = match vector.container_kind.as_deref() {
+                    None | Some("RUNTIME") => Some(ContainerKind::Runtime),
+                    Some("INITCODE") => Some(ContainerKind::Initcode),
+                    Some(_) => None,
+                };
+                let outcome = match (fork.parse::<Spec>(), kind) {
+                    (_, None) => Outcome::Failed(format!(
+                        "unknown container kind {}",
+                        vector.container_kind.as_deref().unwrap_or_default()
+                    )),
+                    (Ok(spec), Some(kind)) => run_vector(&vector.code, spec, kind, expectation),
+                    (Err(_), _) => Outcome::Skipped(format!("no spec for fork {}", fork)),
+                };
+                results.push(FixtureResult {
+                    test: format!("{}/{}", name, key),
+                    fork: fork.clone(),
+                    outcome,
+                });
+            }
+        }
+    }
+    Ok(results)
+}
+
+/// Runs all `.json` fixture files found under `path`, recursively.
+pub fn run_fixtures_dir(path: &Path) -> Result<Vec<FixtureResult>> {
+    let mut entries: Vec<_> = fs::read_dir(path)?.collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|entry| entry.path());
+
+    let mut results = vec![];
+    for entry in entries {
+        let path = entry.path();
+        if path.is_dir() {
+            results.extend(run_fixtures_dir(&path)?);
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            results.extend(run_fixtures(&fs::read_to_string(&path)?)?);
+        }
+    }
+    Ok(results)
+}
+
+fn run_vector(
+    code: &str,
+    spec: Spec,
+    kind: ContainerKind,
+    expectation: &ForkExpectation,
+) -> Outcome {
+    let code = match hex::decode(code.trim_start_matches("0x")) {
+        Ok(code) => code,
+        Err(err) => return Outcome::Failed(format!("invalid hex: {}", err)),
+    };
+    let actual = from_slice_with_spec(&code, spec)
+        .and_then(|container| container.is_valid_eof_with_kind(spec, kind));
+    let exception = expectation.exception.as_deref();
+
+    match (expectation.result, actual) {
+        (true, Ok(())) => Outcome::Passed,
+        (true, Err(err)) => Outcome::Failed(format!("expected success, got {}", err)),
+        (false, Ok(())) => Outcome::Failed(format!(
+            "expected {}, got success",
+            exception.unwrap_or("an exception")
+        )),
+        // Any error will do when the vector does not name one.
+        (false, Err(_)) if exception.is_none() => Outcome::Passed,
+        (false, Err(err)) => {
+            let exception = exception.unwrap_or_default();
+            match exception_matches(exception, &err) {
+                Some(true) => Outcome::Passed,
+                Some(false) => Outcome::Failed(format!("expected {}, got {}", exception, err)),
+                None => Outcome::Skipped(format!(
+                    "unmapped exception {}, rejected with {}",
+                    exception, err
+                )),
+            }
+        }
+    }
+}
+
+/// Whether `error` corresponds to a fixture exception name such as
+/// `EOFException.STACK_UNDERFLOW`. Alternatives separated by `|` are accepted. Returns `None` for
+/// exception names without a known mapping.
+pub fn exception_matches(exception: &str, error: &Error) -> Option<bool> {
+    use Error::*;
+
+    let error = error.kind();
+    let mut known = false;
+    for name in exception.split('|') {
+        let name = name.trim().trim_start_matches("EOFException.");
+        let matches = match name {
+            "INVALID_MAGIC" | "INCOMPLETE_MAGIC" => matches!(error, InvalidMagic),
+            "INVALID_VERSION" | "UNKNOWN_VERSION" => {
+                matches!(error, UnsupportedVersion | UnexpectedEOF)
+            }
+            "MISSING_TYPE_HEADER" => matches!(error, MissingTypeHeader),
+            "MISSING_CODE_HEADER" => matches!(error, MissingCodeHeader),
+            "MISSING_DATA_SECTION" => matches!(error, MissingDataHeader),
+            "MISSING_TERMINATOR" | "MISSING_HEADERS_TERMINATOR" => {
+                matches!(error, MissingTerminator | IncompleteSections)
+            }
+            "INCOMPLETE_SECTION_NUMBER" | "INCOMPLETE_SECTION_SIZE" | "INCOMPLETE_CODE_HEADER" |
+            "INCOMPLETE_DATA_HEADER" => {
+                matches!(error, IncompleteSections | IncompleteSectionSize | UnexpectedEOF)
+            }
+            "UNEXPECTED_HEADER_KIND" => matches!(error, UnsupportedSectionKind),
+            "INVALID_TYPE_SECTION_SIZE" => matches!(error, InvalidTypeSectionSize),
+            "ZERO_SECTION_SIZE" => matches!(
+                error,
+                InvalidCodeSize | InvalidContainerSectionSize | InvalidTypeSectionSize
+            ),
+            "INVALID_SECTION_BODIES_SIZE" => matches!(error, InvalidCodeSize),
+            "TOPLEVEL_CONTAINER_TRUNCATED" => {
+                matches!(error, InvalidCodeSize | TruncatedDataSection)
+            }
+            "EOFCREATE_WITH_TRUNCATED_CONTAINER" => matches!(error, TruncatedDataSection),
+            "ORPHAN_SUBCONTAINER" => matches!(error, OrphanSubcontainer { .. }),
+            "AMBIGUOUS_CONTAINER_KIND" => matches!(error, AmbiguousContainerKind { .. }),
+            "INCOMPATIBLE_CONTAINER_KIND" => matches!(error, IncompatibleContainerKind),
+            "TRAILING_BYTES" => matches!(error, InvalidContainerSize),
+            "TOO_MANY_CODE_SECTIONS" => matches!(error, TooManyCodeSections),
+            "TOO_MANY_CONTAINERS" => matches!(error, TooManyContainerSections),
+            "INVALID_FIRST_SECTION_TYPE" => matches!(error, InvalidSection0Type),
+            "INPUTS_OUTPUTS_NUM_ABOVE_LIMIT" => matches!(error, TooManyInputs | TooManyOutputs),
+            "MAX_STACK_INCREASE_ABOVE_LIMIT" | "MAX_STACK_HEIGHT_ABOVE_LIMIT" => {
+                matches!(error, TooLargeMaxStackHeight | StackOverflow)
+            }
+            "INVALID_MAX_STACK_INCREASE" | "INVALID_MAX_STACK_HEIGHT" => {
+                matches!(error, InvalidMaxStackHeight { .. })
+            }
+            "UNDEFINED_INSTRUCTION" => matches!(error, UndefinedInstruction(_)),
+            "TRUNCATED_INSTRUCTION" => matches!(error, TruncatedImmediate),
+            "INVALID_RJUMP_DESTINATION" => matches!(error, InvalidJumpdest | InvalidBranchCount),
+            "INVALID_CODE_SECTION_INDEX" => matches!(error, InvalidSectionArgument),
+            "INVALID_CONTAINER_SECTION_INDEX" => matches!(error, InvalidContainerSectionIndex),
+            "INVALID_DATALOADN_INDEX" => matches!(error, InvalidDataloadnIndex),
+            "MISSING_STOP_OPCODE" => matches!(error, InvalidCodeTermination),
+            "UNREACHABLE_INSTRUCTIONS" => matches!(error, UnreachableCode),
+            "UNREACHABLE_CODE_SECTIONS" => matches!(error, UnreachableCodeSection),
+            "STACK_UNDERFLOW" => matches!(error, StackUnderflow { .. }),
+            "STACK_OVERFLOW" => matches!(error, StackOverflow),
+            "STACK_HEIGHT_MISMATCH" => matches!(
+                error,
+                ConflictingStack { .. } | InvalidStackHeight { .. } | InvalidOutputs { .. }
+            ),
+            "STACK_HIGHER_THAN_OUTPUTS" => {
+                matches!(error, InvalidStackHeight { .. } | InvalidOutputs { .. })
+            }
+            "INVALID_NON_RETURNING_FLAG" => matches!(error, InvalidNonReturningFlag),
+            "CALLF_TO_NON_RETURNING" => matches!(error, CallfToNonReturningFunction),
+            "JUMPF_DESTINATION_INCOMPATIBLE_OUTPUTS" => {
+                matches!(error, JumpfDestinationIncompatibleOutputs)
+            }
+            _ => continue,
+        };
+        if matches {
+            return Some(true);
+        }
+        known = true;
+    }
+    if known {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_fixtures() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/local");
+        let results = run_fixtures_dir(&path).unwrap();
+
+        for result in &results {
+            if let Outcome::Failed(ref msg) = result.outcome {
+                panic!("{} ({}): {}", result.test, result.fork, msg);
+            }
+        }
+        let passed = results.iter().filter(|r| r.outcome == Outcome::Passed).count();
+        assert_eq!(passed, 26);
+        let skipped: Vec<&Outcome> =
+            results.iter().map(|r| &r.outcome).filter(|o| **o != Outcome::Passed).collect();
+        assert_eq!(skipped, vec![&Outcome::Skipped("no spec for fork Cancun".to_string())]);
+    }
+
+    #[test]
+    fn container_kinds_and_unmapped_exceptions() {
+        let json = r#"{
+            "EOF1_example": {
+                "vectors": {
+                    "0_initcode": {
+                        "code": "0xef00010100040200010001040000000080000000",
+                        "containerKind": "INITCODE",
+                        "results": {
+                            "Osaka": {
+                                "exception": "EOFException.INCOMPATIBLE_CONTAINER_KIND",
+                                "result": false
+                            }
+                        }
+                    },
+                    "1_unknown_kind": {
+                        "code": "0xef00010100040200010001040000000080000000",
+                        "containerKind": "SOMETHING_NEW",
+                        "results": { "Osaka": { "result": true } }
+                    },
+                    "2_unmapped_rejected": {
+                        "code": "0xef000101000402000100010400000000800000",
+                        "results": {
+                            "Osaka": { "exception": "EOFException.SOMETHING_NEW", "result": false }
+                        }
+                    },
+                    "3_unmapped_accepted": {
+                        "code": "0xef00010100040200010001040000000080000000",
+                        "results": {
+                            "Osaka": { "exception": "EOFException.SOMETHING_NEW", "result": false }
+                        }
+                    }
+                }
+            }
+        }"#;
+        let outcomes: Vec<Outcome> =
+            run_fixtures(json).unwrap().into_iter().map(|r| r.outcome).collect();
+
+        assert_eq!(
+            outcomes,
+            vec![
+                Outcome::Passed,
+                Outcome::Failed("unknown container kind SOMETHING_NEW".to_string()),
+                Outcome::Skipped(
+                    "unmapped exception EOFException.SOMETHING_NEW, rejected with Invalid Code \
+                     section size"
+                        .to_string()
+                ),
+                Outcome::Failed("expected EOFException.SOMETHING_NEW, got success".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn exception_names() {
+        let underflow = Error::StackUnderflow { required: 2, actual: 1 }.at(0, Some(2), Some(1));
+
+        assert_eq!(exception_matches("EOFException.STACK_UNDERFLOW", &underflow), Some(true));
+        assert_eq!(
+            exception_matches("EOFException.STACK_OVERFLOW|EOFException.STACK_UNDERFLOW", &underflow),
+            Some(true)
+        );
+        assert_eq!(exception_matches("EOFException.INVALID_MAGIC", &underflow), Some(false));
+        assert_eq!(exception_matches("EOFException.SOMETHING_NEW", &underflow), None);
+    }
+}
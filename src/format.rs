@@ -0,0 +1,138 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::de::from_slice_with_spec;
+use super::error::{Error, Result};
+use super::spec::Spec;
+use super::types::*;
+
+/// Representation of a container on disk or on the command line.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum Format {
+    /// Raw EOF bytecode.
+    Bin,
+    /// Hex encoded EOF bytecode, optionally prefixed with `0x`.
+    Hex,
+    /// The serde representation of `EOFContainer` as JSON.
+    Json,
+    /// The serde representation of `EOFContainer` as YAML.
+    Yaml,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Bin, Format::Hex, Format::Json, Format::Yaml];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Bin => "bin",
+            Format::Hex => "hex",
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+        }
+    }
+
+    /// Guesses the format of `input`: bytecode starting with the EOF magic is binary, text
+    /// starting with `{` is JSON, text made of hex digits is hex, and any other text is YAML.
+    pub fn detect(input: &[u8]) -> Format {
+        if input.starts_with(&EOF_MAGIC.to_be_bytes()) {
+            return Format::Bin;
+        }
+        let text = match std::str::from_utf8(input) {
+            Ok(text) => text.trim(),
+            Err(_) => return Format::Bin,
+        };
+        let digits = strip_hex_prefix(text);
+        if text.starts_with('{') {
+            Format::Json
+        } else if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            Format::Hex
+        } else {
+            Format::Yaml
+        }
+    }
+
+    /// Decodes a container. Binary and hex input must hold the whole data section: a truncated one
+    /// is only valid in a subcontainer, so it would fail validation as a top level container.
+    pub fn decode(&self, input: &[u8], spec: Spec) -> Result<EOFContainer> {
+        match self {
+            Format::Bin => from_slice_with_spec(input, spec),
+            Format::Hex => {
+                let text = std::str::from_utf8(input)
+                    .map_err(|err| Error::Message(format!("invalid hex: {}", err)))?;
+                let code = hex::decode(strip_hex_prefix(text.trim()))
+                    .map_err(|err| Error::Message(format!("invalid hex: {}", err)))?;
+                from_slice_with_spec(&code, spec)
+            }
+            Format::Json => Ok(serde_json::from_slice(input)?),
+            Format::Yaml => Ok(serde_yaml::from_slice(input)?),
+        }
+    }
+
+    /// Encodes a container. Text formats end with a newline.
+    pub fn encode(&self, container: &EOFContainer, spec: Spec) -> Result<Vec<u8>> {
+        let text = match self {
+            Format::Bin => return container.to_bytes_with_spec(spec),
+            Format::Hex => hex::encode(container.to_bytes_with_spec(spec)?) + "\n",
+            Format::Json => serde_json::to_string(container)? + "\n",
+            Format::Yaml => serde_yaml::to_string(container)?,
+        };
+        Ok(text.into_bytes())
+    }
+}
+
+fn strip_hex_prefix(text: &str) -> &str {
+    text.strip_prefix("0x").unwrap_or(text)
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "yml" => Ok(Format::Yaml),
+            name => Format::ALL
+                .into_iter()
+                .find(|format| format.name() == name)
+                .ok_or_else(|| Error::Message(format!("Unknown format: {}", s))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_format() {
+        let code = hex::decode("ef000101000402000100010400000000800000fe").unwrap();
+
+        assert_eq!(Format::detect(&code), Format::Bin);
+        assert_eq!(Format::detect(b"0xef0001010004\n"), Format::Hex);
+        assert_eq!(Format::detect(b" {\"version\":1}"), Format::Json);
+        assert_eq!(Format::detect(b"version: 1\nsections: []\n"), Format::Yaml);
+        assert_eq!("YML".parse::<Format>(), Ok(Format::Yaml));
+    }
+
+    #[test]
+    fn convert_roundtrip() {
+        let code = hex::decode("ef00010100040200010001040002000080000000aabb").unwrap();
+        let container = Format::Bin.decode(&code, Spec::Osaka).unwrap();
+
+        for format in Format::ALL {
+            let encoded = format.encode(&container, Spec::Osaka).unwrap();
+            assert_eq!(Format::detect(&encoded), format);
+            assert_eq!(format.decode(&encoded, Spec::Osaka).unwrap(), container);
+        }
+        assert_eq!(Format::Bin.encode(&container, Spec::Osaka).unwrap(), code);
+
+        // Top level containers cannot have a truncated data section
+        let code = hex::decode("ef00010100040200010001040004000080000000aabb").unwrap();
+        assert_eq!(Format::Bin.decode(&code, Spec::Osaka), Err(Error::InvalidCodeSize));
+    }
+}
@@ -1,13 +1,33 @@
+mod asm;
+mod builder;
 mod de;
+mod disasm;
 mod display;
 mod error;
+mod format;
 mod ser;
+mod spec;
 mod types;
 mod opcodes;
 mod validation;
+mod value;
 
-pub use error::{Result, Error};
-pub use de::from_slice;
-pub use ser::to_bytes;
+pub mod fixtures;
+
+pub use asm::{assemble, assemble_with_spec};
+pub use builder::EOFBuilder;
+pub use error::{Result, Error, Location};
+pub use format::Format;
+pub use disasm::{disassemble, disassemble_code, disassemble_with_spec};
+pub use de::{
+    from_bytes, from_bytes_with_spec, from_slice, from_slice_with_spec, from_slice_ref,
+    from_slice_ref_with_spec, from_slice_relaxed, from_slice_relaxed_with_spec, header_from_slice,
+    header_from_slice_with_spec,
+};
+pub use ser::{to_bytes, to_bytes_with_limit, to_bytes_with_spec};
+pub use spec::Spec;
 pub use types::*;
-pub use validation::EOFValidator;
+pub use validation::{
+    fix_stack_heights, fix_stack_heights_with_spec, max_stack_height, max_stack_heights,
+    max_stack_heights_with_spec, validate_code, ContainerKind, Diagnostic, EOFValidator, Severity,
+};
@@ -1,45 +1,74 @@
-use super::error::Result;
-use super::types::*;
+use serde::Serialize;
+use std::borrow::Cow;
 
-// TODO: implement complete serde serialiser (see ciborium for an example)
+use super::error::{Error, Result};
+use super::spec::Spec;
+use super::types::*;
+use super::value::ValueSerializer;
 
 struct HeaderEntry {
     kind: u8,
-    size: u16,
+    size: u32,
 }
 
-struct Encoder {
+/// Section bodies borrow code and data from the container being encoded.
+struct Encoder<'a> {
+    spec: Spec,
     version: u8,
     headers: Vec<HeaderEntry>,
-    contents: Vec<Vec<u8>>,
+    contents: Vec<Cow<'a, [u8]>>,
 }
 
-// TODO: use proper encoder and not typecasting
-
-impl Encoder {
-    fn encode_types(types: Vec<EOFTypeSectionEntry>) -> Vec<u8> {
+impl<'a> Encoder<'a> {
+    fn encode_types(types: &[EOFTypeSectionEntry]) -> Vec<u8> {
         types
-            .into_iter()
+            .iter()
             .flat_map(|type_entry| {
-                vec![
-                    type_entry.inputs,
-                    type_entry.outputs,
-                    (type_entry.max_stack_height >> 8) as u8,
-                    (type_entry.max_stack_height & 0xff) as u8,
-                ]
+                let [high, low] = type_entry.max_stack_height.to_be_bytes();
+                [type_entry.inputs, type_entry.outputs, high, low]
             })
             .collect()
     }
 
-    fn push_section(&mut self, section: EOFSection) -> Result<()> {
+    fn push_section(&mut self, section: &'a EOFSection) -> Result<()> {
         let section_kind = section.kind();
+        let code_index = self.count(EOF_SECTION_CODE);
+
+        // A truncated data section keeps its declared size in the header
+        let declared_size = match section {
+            EOFSection::Data(data) => Some(data.declared_size as u32),
+            _ => None,
+        };
 
         // Encode content
-        let content = match section {
-            EOFSection::Code(code) => code,
-            EOFSection::Data(data) => data,
-            EOFSection::Type(types) => Self::encode_types(types),
+        let content: Cow<[u8]> = match section {
+            EOFSection::Code(code) if code.is_empty() => {
+                return Err(Error::InvalidCodeSize.at(code_index, None, None));
+            }
+            EOFSection::Code(code) => Cow::Borrowed(code),
+            EOFSection::Data(data) => Cow::Borrowed(&data.data),
+            EOFSection::Type(types) => Cow::Owned(Self::encode_types(types)),
+            EOFSection::Container(_) if !self.spec.supports_container_sections() => {
+                return Err(Error::UnsupportedSectionKind);
+            }
+            EOFSection::Container(container) => Cow::Owned(encode(container, self.spec, None)?),
+        };
+
+        let max_size = match section_kind {
+            EOF_SECTION_CONTAINER if self.spec.container_size_bytes() == 4 => u32::MAX as usize,
+            _ => u16::MAX as usize,
         };
+        if content.len() > max_size {
+            let kind = section.kind_with_spec(self.spec);
+            let error = Error::SectionTooLarge { kind, size: content.len(), spec: self.spec };
+            return Err(match section_kind {
+                EOF_SECTION_CODE => error.at(code_index, None, None),
+                _ => error,
+            });
+        }
+        if declared_size.is_some_and(|size| (size as usize) < content.len()) {
+            return Err(Error::InvalidDataSize);
+        }
 
         let content_len = content.len();
         self.contents.push(content);
@@ -47,81 +76,169 @@ impl Encoder {
         // Store header
         self.headers.push(HeaderEntry {
             kind: section_kind,
-            size: content_len as u16,
+            size: declared_size.unwrap_or(content_len as u32),
         });
 
         Ok(())
     }
 
-    fn finalize(self) -> Result<Vec<u8>> {
-        let mut type_headers: Vec<u8> = self.headers
-            .iter()
-            .filter(|header| header.kind == EOF_SECTION_TYPE)
-            .flat_map(|header| {
-                vec![
-                    header.kind,
-                    (header.size >> 8) as u8,
-                    (header.size & 0xff) as u8,
-                ]
-            })
-            .collect();
-
-        let mut code_sizes: Vec<u8> = self.headers
-            .iter()
-            .filter(|header| header.kind == EOF_SECTION_CODE)
-            .flat_map(|header| {
-                vec![(header.size >> 8) as u8, (header.size & 0xff) as u8]
-            })
-            .collect();
+    fn count(&self, kind: u8) -> usize {
+        self.headers.iter().filter(|header| header.kind == kind).count()
+    }
 
-        let mut code_header: Vec<u8> = vec![
-            EOF_SECTION_CODE,
-            ((code_sizes.len() / 2) >> 8) as u8,
-            ((code_sizes.len() / 2) & 0xff) as u8,
-        ];
+    /// Checks that the section counts fit the header fields and match each other.
+    fn check_sections(&self) -> Result<()> {
+        match self.count(EOF_SECTION_TYPE) {
+            0 => return Err(Error::MissingTypeHeader),
+            1 => {}
+            _ => return Err(Error::DuplicateTypeSection),
+        }
+        let code_sections = self.count(EOF_SECTION_CODE);
+        if code_sections == 0 {
+            return Err(Error::MissingCodeHeader);
+        }
+        if code_sections > EOF_MAX_CODE_SECTIONS {
+            return Err(Error::TooManyCodeSections);
+        }
+        if self.count(EOF_SECTION_CONTAINER) > EOF_MAX_CONTAINER_SECTIONS {
+            return Err(Error::TooManyContainerSections);
+        }
+        match self.count(EOF_SECTION_DATA) {
+            0 => return Err(Error::MissingDataHeader),
+            1 => {}
+            _ => return Err(Error::DuplicateDataSection),
+        }
 
-        let mut data_header: Vec<u8> = self.headers
+        let type_entries = self.headers
             .iter()
-            .filter(|header| header.kind == EOF_SECTION_DATA)
-            .flat_map(|header| {
-                vec![
-                    header.kind,
-                    (header.size >> 8) as u8,
-                    (header.size & 0xff) as u8,
-                ]
-            })
-            .collect();
+            .find(|header| header.kind == EOF_SECTION_TYPE)
+            .map_or(0, |header| header.size as usize / 4);
+        if type_entries != code_sections {
+            return Err(Error::MismatchingCodeAndTypeSections);
+        }
+        Ok(())
+    }
 
-        let mut encoded_contents: Vec<u8> = self.contents.into_iter().flatten().collect();
+    fn finalize(self) -> Result<Vec<u8>> {
+        self.check_sections()?;
+
+        // Sizes of type, code and data sections were checked to fit in two bytes.
+        let sizes = |kind: u8, width: usize| -> Vec<u8> {
+            self.headers
+                .iter()
+                .filter(|header| header.kind == kind)
+                .flat_map(|header| header.size.to_be_bytes()[4 - width..].to_vec())
+                .collect()
+        };
+        let type_sizes = sizes(EOF_SECTION_TYPE, 2);
+        let code_sizes = sizes(EOF_SECTION_CODE, 2);
+        let container_sizes = sizes(EOF_SECTION_CONTAINER, self.spec.container_size_bytes());
+        let data_sizes = sizes(EOF_SECTION_DATA, 2);
+        let code_count = self.count(EOF_SECTION_CODE) as u16;
+        let container_count = self.count(EOF_SECTION_CONTAINER) as u16;
 
         let mut ret = EOF_MAGIC.to_be_bytes().to_vec();
         ret.push(self.version);
-        ret.append(&mut type_headers);
-        ret.append(&mut code_header);
-        ret.append(&mut code_sizes);
-        ret.append(&mut data_header);
+        ret.push(EOF_SECTION_TYPE);
+        ret.extend(type_sizes);
+        ret.push(EOF_SECTION_CODE);
+        ret.extend(code_count.to_be_bytes());
+        ret.extend(code_sizes);
+        if container_count > 0 {
+            ret.push(EOF_SECTION_CONTAINER);
+            ret.extend(container_count.to_be_bytes());
+            ret.extend(container_sizes);
+        }
+        ret.push(self.spec.data_section_kind());
+        ret.extend(data_sizes);
         ret.push(EOF_SECTION_TERMINATOR);
-        ret.append(&mut encoded_contents);
+
+        // Bodies follow the canonical order of the headers: types, code, containers, data. The
+        // sort is stable, so sections of the same kind keep their relative order.
+        let mut bodies: Vec<(u8, Cow<[u8]>)> = self.headers
+            .iter()
+            .map(|header| header.kind)
+            .zip(self.contents)
+            .collect();
+        bodies.sort_by_key(|(kind, _)| *kind);
+        for (_, body) in bodies {
+            ret.extend_from_slice(&body);
+        }
 
         Ok(ret)
     }
 }
 
-pub fn to_bytes(value: EOFContainer) -> Result<Vec<u8>> {
+impl EOFContainer {
+    /// Encodes the container into EOF bytes. Unlike the generic `to_bytes`, this encodes the
+    /// sections directly instead of going through their serde representation.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        self.to_bytes_with_spec(Spec::default())
+    }
+
+    pub fn to_bytes_with_spec(&self, spec: Spec) -> Result<Vec<u8>> {
+        encode(self, spec, None)
+    }
+
+    /// Like `to_bytes_with_spec`, but fails if the encoded container is larger than `max_size`.
+    pub fn to_bytes_with_limit(&self, spec: Spec, max_size: usize) -> Result<Vec<u8>> {
+        encode(self, spec, Some(max_size))
+    }
+}
+
+/// Serializes `value` into EOF bytes.
+///
+/// Besides `EOFContainer`, any type with the same serde shape can be encoded: a struct with
+/// `version` and `sections` fields, whose sections are enum variants named `Type`, `Code`,
+/// `Container` and `Data`. The value is first converted to an `EOFContainer` through its serde
+/// representation; `EOFContainer::to_bytes` encodes a container directly.
+///
+/// Section bodies are always written in canonical order (type, code, containers, data), whatever
+/// the order of `sections`.
+pub fn to_bytes<T: Serialize>(value: T) -> Result<Vec<u8>> {
+    to_bytes_with_spec(value, Spec::default())
+}
+
+pub fn to_bytes_with_spec<T: Serialize>(value: T, spec: Spec) -> Result<Vec<u8>> {
+    encode(&value.serialize(ValueSerializer)?.into_container()?, spec, None)
+}
+
+/// Like `to_bytes_with_spec`, but fails if the encoded container is larger than `max_size`,
+/// e.g. `MAX_CODE_SIZE` (EIP-170) for deployed code or `MAX_INITCODE_SIZE` (EIP-3860).
+pub fn to_bytes_with_limit<T: Serialize>(
+    value: T,
+    spec: Spec,
+    max_size: usize,
+) -> Result<Vec<u8>> {
+    encode(&value.serialize(ValueSerializer)?.into_container()?, spec, Some(max_size))
+}
+
+fn encode(container: &EOFContainer, spec: Spec, max_size: Option<usize>) -> Result<Vec<u8>> {
     let mut encoder = Encoder {
-        version: value.version,
+        spec,
+        version: container.version,
         headers: vec![],
         contents: vec![],
     };
-    for section in value.sections {
+    for section in &container.sections {
         encoder.push_section(section)?;
     }
-    encoder.finalize()
+    let bytes = encoder.finalize()?;
+
+    match max_size {
+        Some(limit) if bytes.len() > limit => {
+            Err(Error::ContainerTooLarge { size: bytes.len(), limit })
+        }
+        _ => Ok(bytes),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::de::{from_slice, from_slice_relaxed, from_slice_with_spec};
+    use super::super::validation::EOFValidator;
+    use proptest::prelude::*;
 
     #[test]
     fn encode_eof_bytes() {
@@ -142,14 +259,263 @@ mod tests {
                 ]),
                 EOFSection::Code(vec![0xfe]),
                 EOFSection::Code(vec![0xfe]),
-                EOFSection::Data(vec![0, 1, 2, 3, 4]),
+                EOFSection::Data(vec![0, 1, 2, 3, 4].into()),
             ],
         };
 
         let serialized = to_bytes(container).unwrap();
         assert_eq!(
             hex::encode(serialized),
-            "ef000101000802000200010001030005000000000001010000fefe0001020304"
+            "ef000101000802000200010001040005000000000001010000fefe0001020304"
         );
     }
+
+    #[test]
+    fn encode_with_spec() {
+        let container = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![
+                    EOFTypeSectionEntry {
+                        inputs: 0,
+                        outputs: 0,
+                        max_stack_height: 0,
+                    },
+                ]),
+                EOFSection::Code(vec![0xfe]),
+                EOFSection::Data(vec![0, 1].into()),
+            ],
+        };
+
+        let serialized = to_bytes_with_spec(container.clone(), Spec::ShanghaiDraft).unwrap();
+        assert_eq!(
+            hex::encode(serialized),
+            "ef000101000402000100010300020000000000fe0001"
+        );
+
+        let mut factory = container.clone();
+        factory.sections.insert(2, EOFSection::Container(container));
+        assert_eq!(
+            to_bytes_with_spec(factory, Spec::CancunDraft),
+            Err(Error::UnsupportedSectionKind)
+        );
+    }
+
+    #[test]
+    fn encode_nested_container() {
+        let subcontainer = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![
+                    EOFTypeSectionEntry {
+                        inputs: 0,
+                        outputs: 0,
+                        max_stack_height: 0,
+                    },
+                ]),
+                EOFSection::Code(vec![0xfe]),
+                EOFSection::Data(vec![].into()),
+            ],
+        };
+        let container = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![
+                    EOFTypeSectionEntry {
+                        inputs: 0,
+                        outputs: 0,
+                        max_stack_height: 0,
+                    },
+                ]),
+                EOFSection::Code(vec![0xfe]),
+                EOFSection::Container(subcontainer),
+                EOFSection::Data(vec![].into()),
+            ],
+        };
+
+        let serialized = to_bytes(container).unwrap();
+        assert_eq!(
+            hex::encode(serialized),
+            "ef00010100040200010001030001000000140400000000000000feef000101000402000100010400000000000000fe"
+        );
+    }
+
+    #[test]
+    fn encode_truncated_data() {
+        let input = "ef00010100040200010001040004000080000000aabb";
+        let mut container = from_slice_relaxed(&hex::decode(input).unwrap()).unwrap();
+        assert_eq!(hex::encode(to_bytes(container.clone()).unwrap()), input);
+
+        // Auxdata fills the data section and patches the declared size
+        container.append_auxdata(&[0xcc, 0xdd, 0xee]).unwrap();
+        assert_eq!(
+            hex::encode(to_bytes(container).unwrap()),
+            "ef00010100040200010001040005000080000000aabbccddee"
+        );
+    }
+
+    #[test]
+    fn encode_mirror_types() {
+        use serde::{Deserialize, Serialize};
+        use super::super::de::from_bytes;
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Function {
+            inputs: u8,
+            outputs: u8,
+            max_stack_height: u16,
+        }
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        enum Section {
+            Type(Vec<Function>),
+            Code(Vec<u8>),
+            Data(Vec<u8>),
+        }
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Contract {
+            version: u8,
+            sections: Vec<Section>,
+        }
+
+        let contract = Contract {
+            version: 1,
+            sections: vec![
+                Section::Type(vec![Function { inputs: 0, outputs: 0x80, max_stack_height: 0 }]),
+                Section::Code(vec![0x00]),
+                Section::Data(vec![0xaa, 0xbb]),
+            ],
+        };
+
+        let serialized = to_bytes(&contract).unwrap();
+        assert_eq!(hex::encode(&serialized), "ef00010100040200010001040002000080000000aabb");
+        assert_eq!(from_bytes::<Contract>(&serialized).unwrap(), contract);
+
+        #[derive(Serialize)]
+        struct Unversioned {
+            sections: Vec<Section>,
+        }
+        assert!(matches!(
+            to_bytes(Unversioned { sections: vec![] }),
+            Err(Error::Message(_))
+        ));
+    }
+
+    #[test]
+    fn encode_errors() {
+        let container = |types: usize, codes: Vec<Vec<u8>>, data: Option<Vec<u8>>| {
+            let entry = EOFTypeSectionEntry { inputs: 0, outputs: 0x80, max_stack_height: 0 };
+            let mut sections = vec![EOFSection::Type(vec![entry; types])];
+            sections.extend(codes.into_iter().map(EOFSection::Code));
+            sections.extend(data.map(|data| EOFSection::Data(data.into())));
+            EOFContainer { version: 1, sections }
+        };
+
+        assert_eq!(
+            to_bytes(container(1, vec![vec![0; 0x10000]], Some(vec![]))),
+            Err(Error::SectionTooLarge { kind: EOF_SECTION_CODE, size: 0x10000, spec: Spec::Osaka }
+                .at(0, None, None))
+        );
+        assert_eq!(
+            to_bytes(container(1, vec![vec![0]], Some(vec![0; 0x10000]))),
+            Err(Error::SectionTooLarge { kind: EOF_SECTION_DATA, size: 0x10000, spec: Spec::Osaka })
+        );
+        assert_eq!(
+            to_bytes(container(2, vec![vec![0], vec![]], Some(vec![]))),
+            Err(Error::InvalidCodeSize.at(1, None, None))
+        );
+        assert_eq!(
+            to_bytes(container(2, vec![vec![0]], Some(vec![]))),
+            Err(Error::MismatchingCodeAndTypeSections)
+        );
+        assert_eq!(to_bytes(container(1, vec![vec![0]], None)), Err(Error::MissingDataHeader));
+        assert_eq!(
+            to_bytes(container(1025, vec![vec![0]; 1025], Some(vec![]))),
+            Err(Error::TooManyCodeSections)
+        );
+
+        let large = container(1, vec![vec![0; MAX_CODE_SIZE]], Some(vec![]));
+        assert!(to_bytes_with_limit(&large, Spec::Osaka, MAX_INITCODE_SIZE).is_ok());
+        assert_eq!(
+            to_bytes_with_limit(&large, Spec::Osaka, MAX_CODE_SIZE),
+            Err(Error::ContainerTooLarge { size: MAX_CODE_SIZE + 19, limit: MAX_CODE_SIZE })
+        );
+    }
+
+    #[test]
+    fn encode_canonical_order() {
+        let input = "ef00010100040200010001040002000080000000aabb";
+        let mut container = from_slice(&hex::decode(input).unwrap()).unwrap();
+        container.sections.reverse();
+        assert_eq!(hex::encode(to_bytes(container.clone()).unwrap()), input);
+
+        container.sections.push(EOFSection::Data(vec![].into()));
+        assert_eq!(to_bytes(container), Err(Error::DuplicateDataSection));
+    }
+
+    fn arb_container(depth: u32) -> impl Strategy<Value = EOFContainer> {
+        let code = prop::collection::vec(any::<u8>(), 1..32);
+        let entry = (any::<u8>(), any::<u8>(), any::<u16>()).prop_map(
+            |(inputs, outputs, max_stack_height)| EOFTypeSectionEntry {
+                inputs,
+                outputs,
+                max_stack_height,
+            },
+        );
+        let subcontainers = if depth == 0 {
+            Just(vec![]).boxed()
+        } else {
+            prop::collection::vec(arb_container(depth - 1), 0..3).boxed()
+        };
+
+        (
+            prop::collection::vec((entry, code), 1..5),
+            subcontainers,
+            prop::collection::vec(any::<u8>(), 0..32),
+        )
+            .prop_map(|(functions, subcontainers, data)| {
+                let (types, codes): (Vec<_>, Vec<_>) = functions.into_iter().unzip();
+                let mut sections = vec![EOFSection::Type(types)];
+                sections.extend(codes.into_iter().map(EOFSection::Code));
+                sections.extend(subcontainers.into_iter().map(EOFSection::Container));
+                sections.push(EOFSection::Data(data.into()));
+                EOFContainer { version: 1, sections }
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn roundtrip(container in arb_container(2)) {
+            let encoded = container.to_bytes().unwrap();
+            prop_assert_eq!(&to_bytes(&container).unwrap(), &encoded);
+            prop_assert_eq!(from_slice(&encoded).unwrap(), container);
+        }
+
+        #[test]
+        fn roundtrip_shuffled(container in arb_container(1), ranks in any::<[u8; 4]>()) {
+            // Reorder the kinds, keeping sections of the same kind in order
+            let mut shuffled = container.clone();
+            shuffled.sections.sort_by_key(|section| ranks[section.kind() as usize - 1]);
+            prop_assert_eq!(to_bytes(&shuffled).unwrap(), to_bytes(&container).unwrap());
+        }
+
+        #[test]
+        fn corrupted_never_panics(
+            container in arb_container(2),
+            corruptions in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 0..8),
+        ) {
+            let mut encoded = to_bytes(&container).unwrap();
+            for (index, byte) in corruptions {
+                let index = index.index(encoded.len());
+                encoded[index] = byte;
+            }
+            for spec in Spec::ALL {
+                let _ = container.validate_all_with_spec(spec);
+                if let Ok(decoded) = from_slice_with_spec(&encoded, spec) {
+                    let _ = decoded.validate_all_with_spec(spec);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,126 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::error::Error;
+use super::types::*;
+
+/// Revision of the EOF specification used when decoding, encoding and validating containers.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+pub enum Spec {
+    /// EIP-3540/3670/4200/4750/5450 as drafted for Shanghai.
+    ShanghaiDraft,
+    /// The Shanghai draft with control flow instructions moved to 0xe0-0xe5.
+    CancunDraft,
+    /// The full EOF instruction set with container sections of 2-byte size.
+    Prague,
+    /// The final EOFv1 layout.
+    #[default]
+    Osaka,
+}
+
+impl Spec {
+    pub const ALL: [Spec; 4] = [Spec::ShanghaiDraft, Spec::CancunDraft, Spec::Prague, Spec::Osaka];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Spec::ShanghaiDraft => "ShanghaiDraft",
+            Spec::CancunDraft => "CancunDraft",
+            Spec::Prague => "Prague",
+            Spec::Osaka => "Osaka",
+        }
+    }
+
+    /// Section kind used for the data section header.
+    pub fn data_section_kind(&self) -> u8 {
+        match self {
+            Spec::ShanghaiDraft | Spec::CancunDraft => EOF_SECTION_DATA_DRAFT,
+            Spec::Prague | Spec::Osaka => EOF_SECTION_DATA,
+        }
+    }
+
+    /// Name of the section with the given header kind under this spec.
+    pub fn section_name(&self, kind: u8) -> &'static str {
+        match kind {
+            EOF_SECTION_TYPE => "Type",
+            EOF_SECTION_CODE => "Code",
+            kind if kind == self.data_section_kind() => "Data",
+            EOF_SECTION_CONTAINER if self.supports_container_sections() => "Container",
+            _ => "Unknown",
+        }
+    }
+
+    pub fn supports_container_sections(&self) -> bool {
+        matches!(self, Spec::Prague | Spec::Osaka)
+    }
+
+    /// Number of bytes used to encode each container section size.
+    pub fn container_size_bytes(&self) -> usize {
+        match self {
+            Spec::Osaka => 4,
+            _ => 2,
+        }
+    }
+
+    /// Whether type entries can mark a function as non-returning.
+    pub fn supports_non_returning_functions(&self) -> bool {
+        matches!(self, Spec::Prague | Spec::Osaka)
+    }
+
+    /// Whether code sections that are never called or jumped to make a container invalid.
+    pub fn requires_reachable_code_sections(&self) -> bool {
+        matches!(self, Spec::Prague | Spec::Osaka)
+    }
+
+    /// Whether the RJUMPV immediate is the maximum table index rather than the table length.
+    pub fn rjumpv_max_index(&self) -> bool {
+        !matches!(self, Spec::ShanghaiDraft)
+    }
+}
+
+impl fmt::Display for Spec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Spec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_lowercase().replace(['-', '_'], "");
+        Spec::ALL
+            .into_iter()
+            .find(|spec| spec.name().to_ascii_lowercase() == name)
+            .ok_or_else(|| Error::Message(format!("Unknown spec: {}", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_spec_names() {
+        assert_eq!("osaka".parse::<Spec>(), Ok(Spec::Osaka));
+        assert_eq!("Prague".parse::<Spec>(), Ok(Spec::Prague));
+        assert_eq!("shanghai-draft".parse::<Spec>(), Ok(Spec::ShanghaiDraft));
+        assert_eq!("CancunDraft".parse::<Spec>(), Ok(Spec::CancunDraft));
+        assert!("london".parse::<Spec>().is_err());
+        assert_eq!(Spec::default(), Spec::Osaka);
+    }
+
+    #[test]
+    fn section_names() {
+        assert_eq!(Spec::CancunDraft.section_name(EOF_SECTION_DATA_DRAFT), "Data");
+        assert_eq!(Spec::CancunDraft.section_name(EOF_SECTION_DATA), "Unknown");
+        assert_eq!(Spec::Osaka.section_name(EOF_SECTION_CONTAINER), "Container");
+        assert_eq!(Spec::Osaka.section_name(EOF_SECTION_DATA), "Data");
+
+        let error = Error::SectionTooLarge {
+            kind: Spec::CancunDraft.data_section_kind(),
+            size: 0x10000,
+            spec: Spec::CancunDraft,
+        };
+        assert_eq!(error.to_string(), "Data section too large (65536 bytes)");
+    }
+}
@@ -1,12 +1,31 @@
 use serde::{Deserialize, Serialize, Serializer, Deserializer};
+use serde::ser::SerializeStruct;
+use serde::de::{self, SeqAccess, Visitor};
 use hex::FromHex;
+use std::fmt;
+
+use super::error::Error;
+use super::spec::Spec;
 
 pub const EOF_MAGIC: u16 = 0xef00;
 pub const EOF_VERSION_1: u8 = 1;
 pub const EOF_SECTION_TERMINATOR: u8 = 0;
 pub const EOF_SECTION_TYPE: u8 = 1;
 pub const EOF_SECTION_CODE: u8 = 2;
-pub const EOF_SECTION_DATA: u8 = 3;
+pub const EOF_SECTION_CONTAINER: u8 = 3;
+pub const EOF_SECTION_DATA: u8 = 4;
+/// Data section kind of the Shanghai and Cancun drafts, which had no container sections.
+pub const EOF_SECTION_DATA_DRAFT: u8 = 3;
+pub const EOF_MAX_CODE_SECTIONS: usize = 1024;
+pub const EOF_MAX_CONTAINER_SECTIONS: usize = 256;
+/// Deepest subcontainer nesting accepted by the decoder and validator. This is not a spec limit,
+/// it bounds their recursion on hostile input.
+pub const EOF_MAX_CONTAINER_DEPTH: usize = 256;
+pub const EOF_NON_RETURNING_FUNCTION: u8 = 0x80;
+/// Maximum size of deployed code (EIP-170).
+pub const MAX_CODE_SIZE: usize = 0x6000;
+/// Maximum size of initcode (EIP-3860).
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
 
 pub type EOFVersion = u8;
 
@@ -20,9 +39,119 @@ pub struct EOFContainer {
 pub enum EOFSection {
     #[serde(serialize_with = "serialize_bytes", deserialize_with = "deserialize_hexstr")]
     Code(Vec<u8>),
-    #[serde(serialize_with = "serialize_bytes", deserialize_with = "deserialize_hexstr")]
-    Data(Vec<u8>),
+    Data(EOFData),
     Type(Vec<EOFTypeSectionEntry>),
+    Container(EOFContainer),
+}
+
+/// Data section contents together with the size declared in the header.
+///
+/// Only containers deployed by RETURNCODE may have fewer bytes present than declared; the
+/// missing bytes are supplied as auxdata at deploy time.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct EOFData {
+    pub data: Vec<u8>,
+    pub declared_size: u16,
+}
+
+impl EOFData {
+    /// A complete data section, declaring exactly the bytes given.
+    ///
+    /// The declared size is clamped to `u16::MAX`, the largest the header can hold. Longer data
+    /// is rejected with `SectionTooLarge` by the encoder and the validator.
+    pub fn new(data: Vec<u8>) -> Self {
+        let declared_size = u16::try_from(data.len()).unwrap_or(u16::MAX);
+        Self { data, declared_size }
+    }
+
+    /// Returns true if fewer bytes are present than the header declares.
+    pub fn is_truncated(&self) -> bool {
+        self.data.len() < self.declared_size as usize
+    }
+
+    /// Appends deploy-time auxdata and patches the declared size to the new length. Fails if the
+    /// data would still be shorter than declared or no longer fits the header size field.
+    pub fn append_auxdata(&mut self, auxdata: &[u8]) -> Result<(), Error> {
+        let size = self.data.len() + auxdata.len();
+        if size > u16::MAX as usize {
+            return Err(Error::InvalidDataSize);
+        }
+        if size < self.declared_size as usize {
+            return Err(Error::TruncatedDataSection);
+        }
+        self.data.extend_from_slice(auxdata);
+        self.declared_size = size as u16;
+        Ok(())
+    }
+}
+
+impl From<Vec<u8>> for EOFData {
+    fn from(data: Vec<u8>) -> Self {
+        Self::new(data)
+    }
+}
+
+// Complete data sections keep the plain hex string form, truncated ones also record the
+// declared size.
+impl Serialize for EOFData {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.data.len() == self.declared_size as usize {
+            return serialize_bytes(&self.data, s);
+        }
+        let mut state = s.serialize_struct("EOFData", 2)?;
+        state.serialize_field("data", &Bytes(&self.data))?;
+        state.serialize_field("declared_size", &self.declared_size)?;
+        state.end()
+    }
+}
+
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_bytes(self.0, s)
+    }
+}
+
+impl<'de> Deserialize<'de> for EOFData {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Complete(#[serde(deserialize_with = "deserialize_hexstr")] Vec<u8>),
+            Truncated {
+                #[serde(deserialize_with = "deserialize_hexstr")]
+                data: Vec<u8>,
+                declared_size: u16,
+            },
+        }
+
+        Ok(match Repr::deserialize(d)? {
+            Repr::Complete(data) => EOFData::new(data),
+            Repr::Truncated { data, declared_size } => EOFData { data, declared_size },
+        })
+    }
+}
+
+impl EOFContainer {
+    /// Appends auxdata to the data section, see `EOFData::append_auxdata`.
+    pub fn append_auxdata(&mut self, auxdata: &[u8]) -> Result<(), Error> {
+        for section in &mut self.sections {
+            if let EOFSection::Data(data) = section {
+                return data.append_auxdata(auxdata);
+            }
+        }
+        Err(Error::MissingDataHeader)
+    }
 }
 
 #[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
@@ -32,45 +161,202 @@ pub struct EOFTypeSectionEntry {
     pub max_stack_height: u16,
 }
 
+/// Section sizes declared in an EOF header.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct EOFHeader {
+    pub version: EOFVersion,
+    pub type_size: u16,
+    pub code_sizes: Vec<u16>,
+    pub container_sizes: Vec<u32>,
+    pub data_size: u16,
+    /// Length of the header in bytes, from the magic up to and including the terminator.
+    pub header_len: usize,
+}
+
+impl EOFHeader {
+    /// Offset of the type section body.
+    pub fn type_offset(&self) -> usize {
+        self.header_len
+    }
+
+    /// Offsets of the code section bodies.
+    pub fn code_offsets(&self) -> Vec<usize> {
+        let start = self.type_offset() + self.type_size as usize;
+        offsets(start, self.code_sizes.iter().map(|size| *size as usize))
+    }
+
+    /// Offsets of the container section bodies.
+    pub fn container_offsets(&self) -> Vec<usize> {
+        let start = self.type_offset() +
+            self.type_size as usize +
+            self.code_sizes.iter().map(|size| *size as usize).sum::<usize>();
+        offsets(start, self.container_sizes.iter().map(|size| *size as usize))
+    }
+
+    /// Offset of the data section body.
+    pub fn data_offset(&self) -> usize {
+        self.type_offset() +
+            self.type_size as usize +
+            self.code_sizes.iter().map(|size| *size as usize).sum::<usize>() +
+            self.container_sizes.iter().map(|size| *size as usize).sum::<usize>()
+    }
+
+    /// Total container size implied by the header.
+    pub fn container_size(&self) -> usize {
+        self.data_offset() + self.data_size as usize
+    }
+}
+
+fn offsets(start: usize, sizes: impl Iterator<Item = usize>) -> Vec<usize> {
+    sizes
+        .scan(start, |offset, size| {
+            let current = *offset;
+            *offset += size;
+            Some(current)
+        })
+        .collect()
+}
+
+/// Borrowed view of an EOF container whose sections point into the decoded bytes.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct EOFContainerRef<'a> {
+    pub version: EOFVersion,
+    pub sections: Vec<EOFSectionRef<'a>>,
+}
+
+/// Borrowed counterpart of `EOFSection`. Type and container sections are kept as raw bytes.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum EOFSectionRef<'a> {
+    Code(&'a [u8]),
+    Data(&'a [u8]),
+    Type(&'a [u8]),
+    Container(&'a [u8]),
+}
+
+impl<'a> EOFSectionRef<'a> {
+    /// Header kind of the section, numbered as in Prague and Osaka.
+    pub fn kind(&self) -> u8 {
+        match self {
+            EOFSectionRef::Code(_) => EOF_SECTION_CODE,
+            EOFSectionRef::Data(_) => EOF_SECTION_DATA,
+            EOFSectionRef::Type(_) => EOF_SECTION_TYPE,
+            EOFSectionRef::Container(_) => EOF_SECTION_CONTAINER,
+        }
+    }
+
+    /// Header kind of the section under the given spec.
+    pub fn kind_with_spec(&self, spec: Spec) -> u8 {
+        match self {
+            EOFSectionRef::Data(_) => spec.data_section_kind(),
+            _ => self.kind(),
+        }
+    }
+
+    /// Section body as it appears in the container.
+    pub fn bytes(&self) -> &'a [u8] {
+        match *self {
+            EOFSectionRef::Code(bytes) |
+            EOFSectionRef::Data(bytes) |
+            EOFSectionRef::Type(bytes) |
+            EOFSectionRef::Container(bytes) => bytes,
+        }
+    }
+}
+
+impl EOFTypeSectionEntry {
+    /// Returns false for functions marked as non-returning (outputs = 0x80).
+    pub fn is_returning(&self) -> bool {
+        self.outputs != EOF_NON_RETURNING_FUNCTION
+    }
+}
+
 impl EOFSection {
+    /// Header kind of the section, numbered as in Prague and Osaka.
     pub fn kind(&self) -> u8 {
         match self {
             EOFSection::Code(_) => EOF_SECTION_CODE,
             EOFSection::Data(_) => EOF_SECTION_DATA,
             EOFSection::Type(_) => EOF_SECTION_TYPE,
+            EOFSection::Container(_) => EOF_SECTION_CONTAINER,
+        }
+    }
+
+    /// Header kind of the section under the given spec.
+    pub fn kind_with_spec(&self, spec: Spec) -> u8 {
+        match self {
+            EOFSection::Data(_) => spec.data_section_kind(),
+            _ => self.kind(),
         }
     }
 
     pub(crate) fn priority(&self) -> u8 {
         match self {
             EOFSection::Code(_) => 2,
-            EOFSection::Data(_) => 3,
+            EOFSection::Data(_) => 4,
             EOFSection::Type(_) => 1,
+            EOFSection::Container(_) => 3,
         }
     }
 }
 
+// Human-readable formats use hex strings, binary formats raw bytes.
 fn serialize_bytes<S, T>(x: T, s: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
     T: AsRef<[u8]>,
 {
-    s.serialize_str(&hex::encode(x.as_ref()))
+    if s.is_human_readable() {
+        s.serialize_str(&hex::encode(x.as_ref()))
+    } else {
+        s.serialize_bytes(x.as_ref())
+    }
 }
 
 fn deserialize_hexstr<'de, D>(d: D) -> Result<Vec<u8>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    use serde::de::Error;
-    String::deserialize(d).and_then(|string| {
-        Vec::from_hex(&string).map_err(|err| Error::custom(err.to_string()))
-    })
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a hex string or bytes")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Vec<u8>, E> {
+            Vec::from_hex(v).map_err(|err| E::custom(err.to_string()))
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(v.to_vec())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+            Ok(v)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+
+    if d.is_human_readable() {
+        d.deserialize_any(BytesVisitor)
+    } else {
+        d.deserialize_byte_buf(BytesVisitor)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::validation::EOFValidator;
 
     #[test]
     fn encode_json() {
@@ -91,7 +377,7 @@ mod tests {
                 ]),
                 EOFSection::Code(vec![0xfe]),
                 EOFSection::Code(vec![0xfe]),
-                EOFSection::Data(vec![0, 1, 2, 3, 4]),
+                EOFSection::Data(vec![0, 1, 2, 3, 4].into()),
             ],
         };
         let serialized = serde_json::to_string(&container).unwrap();
@@ -100,4 +386,79 @@ mod tests {
             "{\"version\":1,\"sections\":[{\"Type\":[{\"inputs\":0,\"outputs\":0,\"max_stack_height\":0},{\"inputs\":1,\"outputs\":1,\"max_stack_height\":0}]},{\"Code\":\"fe\"},{\"Code\":\"fe\"},{\"Data\":\"0001020304\"}]}"
         );
     }
+
+    #[test]
+    fn append_auxdata() {
+        let mut data = EOFData {
+            data: vec![0xaa, 0xbb],
+            declared_size: 4,
+        };
+        assert!(data.is_truncated());
+        assert_eq!(data.append_auxdata(&[0xcc]), Err(Error::TruncatedDataSection));
+        assert_eq!(data.data, vec![0xaa, 0xbb]);
+
+        data.append_auxdata(&[0xcc, 0xdd, 0xee]).unwrap();
+        assert_eq!(data, EOFData::new(vec![0xaa, 0xbb, 0xcc, 0xdd, 0xee]));
+        assert!(!data.is_truncated());
+
+        let mut data = EOFData::new(vec![0; u16::MAX as usize]);
+        assert_eq!(data.append_auxdata(&[0]), Err(Error::InvalidDataSize));
+
+        let mut container = EOFContainer {
+            version: 1,
+            sections: vec![EOFSection::Code(vec![0x00])],
+        };
+        assert_eq!(container.append_auxdata(&[0]), Err(Error::MissingDataHeader));
+    }
+
+    #[test]
+    fn section_kinds() {
+        let data = EOFSection::Data(vec![0xaa].into());
+        assert_eq!(data.kind(), EOF_SECTION_DATA);
+        assert_eq!(data.kind_with_spec(Spec::Osaka), EOF_SECTION_DATA);
+        assert_eq!(data.kind_with_spec(Spec::CancunDraft), EOF_SECTION_DATA_DRAFT);
+        assert_eq!(EOFSectionRef::Data(&[0xaa]).kind_with_spec(Spec::ShanghaiDraft), 3);
+
+        let code = EOFSection::Code(vec![0x00]);
+        assert_eq!(code.kind_with_spec(Spec::CancunDraft), EOF_SECTION_CODE);
+    }
+
+    #[test]
+    fn oversized_data() {
+        let data = EOFData::new(vec![0; 0x10000]);
+        assert_eq!(data.declared_size, u16::MAX);
+
+        let container = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![EOFTypeSectionEntry {
+                    inputs: 0,
+                    outputs: 0x80,
+                    max_stack_height: 0,
+                }]),
+                EOFSection::Code(vec![0x00]),
+                EOFSection::Data(data),
+            ],
+        };
+        let error =
+            Error::SectionTooLarge { kind: EOF_SECTION_DATA, size: 0x10000, spec: Spec::Osaka };
+        assert_eq!(container.is_valid_eof(), Err(error.clone()));
+        assert_eq!(container.to_bytes(), Err(error));
+    }
+
+    #[test]
+    fn truncated_data_json() {
+        let data = EOFSection::Data(EOFData {
+            data: vec![0xaa, 0xbb],
+            declared_size: 4,
+        });
+        let serialized = serde_json::to_string(&data).unwrap();
+        assert_eq!(serialized, "{\"Data\":{\"data\":\"aabb\",\"declared_size\":4}}");
+        assert_eq!(serde_json::from_str::<EOFSection>(&serialized).unwrap(), data);
+
+        let data = EOFSection::Data(vec![0xaa, 0xbb].into());
+        let serialized = serde_json::to_string(&data).unwrap();
+        assert_eq!(serialized, "{\"Data\":\"aabb\"}");
+        assert_eq!(serde_json::from_str::<EOFSection>(&serialized).unwrap(), data);
+    }
 }
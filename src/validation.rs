@@ -1,289 +1,775 @@
-use std::collections::HashSet;
-use std::collections::HashMap;
+use std::fmt;
 
 use super::error::{Error, Result};
+use super::spec::Spec;
 use super::types::*;
 use super::opcodes::*;
 
+/// How a container is executed, which decides the instructions it may end execution with.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub enum ContainerKind {
+    /// Deployed code, which cannot use RETURNCODE. Subcontainers referenced by RETURNCODE are of
+    /// this kind.
+    #[default]
+    Runtime,
+    /// Code creating a contract, which cannot use STOP or RETURN. Subcontainers referenced by
+    /// EOFCREATE are of this kind.
+    Initcode,
+}
+
+/// A subcontainer index, and the kind of container an instruction referencing it requires.
+type SubcontainerReference = (usize, ContainerKind);
+
+/// Validates a single code section of a container of the given kind.
+///
+/// Returns the subcontainers referenced by EOFCREATE and RETURNCODE, with the kind each
+/// reference requires them to be.
 pub fn validate_code(
     function_id: usize,
-    code: &Vec<u8>,
+    code: &[u8],
     types: &[EOFTypeSectionEntry],
-) -> Result<()> {
-    let mut worklist: HashMap<u16, (u16, bool)> = HashMap::new();
-    let mut stack_heights: HashMap<u16, u16> = HashMap::new();
-    let mut immediates: HashSet<u16> = HashSet::new();
-    let mut rjumpdests: HashSet<u16> = HashSet::new();
-    let mut current_stack_height: u16 = types[function_id].inputs as u16;
-    let mut max_stack_height: u16 = current_stack_height;
-    let mut ends_with_terminating_instruction = false;
-    let mut visiting = true;
-    let mut i = 0;
+    data_size: usize,
+    container_count: usize,
+    kind: ContainerKind,
+    spec: Spec,
+) -> Result<Vec<SubcontainerReference>> {
+    let (max_stack_height, returns, references) =
+        analyze_code(function_id, code, types, data_size, container_count, Some(kind), spec)?;
+    let type_entry = &types[function_id];
+
+    if max_stack_height != type_entry.max_stack_height {
+        return Err(Error::InvalidMaxStackHeight {
+            declared: type_entry.max_stack_height,
+            computed: max_stack_height,
+        }
+        .at(function_id, None, None));
+    }
 
-    worklist.insert(i as u16, (current_stack_height, visiting));
-    loop {
-        if i >= code.len() {
-            break;
+    if spec.supports_non_returning_functions() && returns != type_entry.is_returning() {
+        return Err(Error::InvalidNonReturningFlag.at(function_id, None, None));
+    }
+
+    Ok(references)
+}
+
+/// Checks every instruction of a code section, returning its maximum stack height, whether it
+/// contains a RETF or a JUMPF to a returning function, and the subcontainers it references. The
+/// rules of the container kind are only checked if `kind` is given.
+///
+/// A first pass marks where each instruction starts in a bitmap, so that jump destinations can be
+/// checked against instruction boundaries. Stack heights are then tracked as a `(min, max)` range
+/// per code offset in a single forward pass: forward edges widen the range at their target, while
+/// backward edges must match the range already recorded there exactly. Both passes visit every
+/// byte a bounded number of times and allocate nothing per instruction.
+fn analyze_code(
+    function_id: usize,
+    code: &[u8],
+    types: &[EOFTypeSectionEntry],
+    data_size: usize,
+    container_count: usize,
+    kind: Option<ContainerKind>,
+    spec: Spec,
+) -> Result<(u16, bool, Vec<SubcontainerReference>)> {
+    let at = |error: Error, pc: usize| error.at(function_id, Some(pc), Some(code[pc]));
+    let in_section = |error: Error| error.at(function_id, None, None);
+
+    if code.is_empty() {
+        return Err(in_section(Error::InvalidCodeSize));
+    }
+
+    // Decode every instruction and mark where each one starts.
+    let mut instruction_starts = Bitmap::new(code.len());
+    let mut i = 0;
+    while i < code.len() {
+        let op = OpCode::from(code[i], spec)
+            .map_err(|_| at(Error::UndefinedInstruction(code[i]), i))?;
+        if op.name == "RJUMPV" && i + 1 < code.len() && rjumpv_count(code[i + 1], spec) == 0 {
+            return Err(at(Error::InvalidBranchCount, i));
+        }
+        let size = instruction_size(code, i, &op, spec);
+        if i + size > code.len() {
+            return Err(at(Error::TruncatedImmediate, i));
         }
-        match OpCode::from(code[i]) {
-            Ok(op) => {
-                if current_stack_height < (op.stack_inputs as u16) {
-                    return Err(Error::StackUnderflow);
+        instruction_starts.set(i);
+        i += size;
+    }
+
+    let type_entry = types.get(function_id).ok_or_else(|| in_section(Error::InvalidCodeHeader))?;
+    let inputs = type_entry.inputs as u16;
+    let mut stack_heights = vec![UNVISITED; code.len()];
+    stack_heights[0] = (inputs, inputs);
+    let mut max_stack_height = inputs;
+    let mut returns = false;
+    let mut references = Vec::new();
+
+    let mut i = 0;
+    while i < code.len() {
+        let op = OpCode::from(code[i], spec)?;
+        let size = instruction_size(code, i, &op, spec);
+        let (stack_min, stack_max) = match stack_heights[i] {
+            UNVISITED => return Err(at(Error::UnreachableCode, i)),
+            range => range,
+        };
+        // DUPN, SWAPN and EXCHANGE take their stack depth from the immediate, CALLF its stack
+        // effect from the type entry of the called function.
+        let (stack_inputs, stack_outputs) = match op.name {
+            "CALLF" => {
+                let section = u16::from_be_bytes([code[i + 1], code[i + 2]]);
+                let target = types
+                    .get(section as usize)
+                    .ok_or_else(|| at(Error::InvalidSectionArgument, i))?;
+                if !target.is_returning() {
+                    return Err(at(Error::CallfToNonReturningFunction, i));
                 }
+                (target.inputs as u16, target.outputs as u16)
+            }
+            "DUPN" => (code[i + 1] as u16 + 1, code[i + 1] as u16 + 2),
+            "SWAPN" => (code[i + 1] as u16 + 2, code[i + 1] as u16 + 2),
+            "EXCHANGE" => {
+                let n = (code[i + 1] >> 4) as u16 + 1;
+                let m = (code[i + 1] & 0x0f) as u16 + 1;
+                (n + m + 1, n + m + 1)
+            }
+            _ => (op.stack_inputs as u16, op.stack_outputs as u16),
+        };
+
+        if stack_min < stack_inputs {
+            return Err(at(
+                Error::StackUnderflow {
+                    required: stack_inputs,
+                    actual: stack_min,
+                },
+                i,
+            ));
+        }
 
-                stack_heights.insert(i as u16, current_stack_height);
-                current_stack_height = current_stack_height - (op.stack_inputs as u16) +
-                    (op.stack_outputs as u16);
+        match op.name {
+            "CALLF" => {
+                let section = u16::from_be_bytes([code[i + 1], code[i + 2]]);
 
-                if current_stack_height > max_stack_height {
-                    max_stack_height = current_stack_height;
+                // The callee's frame starts with its inputs already on the stack.
+                let target = &types[section as usize];
+                if (stack_max - stack_inputs).saturating_add(target.max_stack_height) > 1024 {
+                    return Err(at(Error::StackOverflow, i));
+                }
+            }
+            "DATALOADN" => {
+                let offset = u16::from_be_bytes([code[i + 1], code[i + 2]]);
+
+                if offset as usize + 32 > data_size {
+                    return Err(at(Error::InvalidDataloadnIndex, i));
+                }
+            }
+            "EOFCREATE" | "RETURNCODE" | "RETURNCONTRACT" => {
+                let index = code[i + 1] as usize;
+                if index >= container_count {
+                    return Err(at(Error::InvalidContainerSectionIndex, i));
                 }
 
-                if op.immediates as usize > code[i + 1..].len() {
-                    return Err(Error::TruncatedImmediate);
+                let referenced = if op.name == "EOFCREATE" {
+                    ContainerKind::Initcode
+                } else {
+                    ContainerKind::Runtime
+                };
+                if referenced == ContainerKind::Runtime && kind == Some(ContainerKind::Runtime) {
+                    return Err(at(Error::IncompatibleContainerKind, i));
+                }
+                references.push((index, referenced));
+            }
+            "STOP" | "RETURN" if kind == Some(ContainerKind::Initcode) => {
+                return Err(at(Error::IncompatibleContainerKind, i));
+            }
+            "RETF" => {
+                if !type_entry.is_returning() {
+                    return Err(at(Error::InvalidNonReturningFlag, i));
+                }
+                let outputs = type_entry.outputs as u16;
+                if stack_min != outputs || stack_max != outputs {
+                    return Err(at(
+                        Error::InvalidOutputs {
+                            expected: outputs,
+                            actual: if stack_max != outputs { stack_max } else { stack_min },
+                        },
+                        i,
+                    ));
+                }
+                returns = true;
+            }
+            "JUMPF" => {
+                let section = u16::from_be_bytes([code[i + 1], code[i + 2]]);
+
+                let target = types
+                    .get(section as usize)
+                    .ok_or_else(|| at(Error::InvalidSectionArgument, i))?;
+
+                if stack_max
+                    .saturating_add(target.max_stack_height)
+                    .saturating_sub(target.inputs as u16) > 1024
+                {
+                    return Err(at(Error::StackOverflow, i));
                 }
-                match op.name {
-                    "CALLF" => {
-                        let section: [u8; 2] = code[i + 1..i + 3].try_into().unwrap();
-                        let section = u16::from_be_bytes(section);
-
-                        if section as usize >= types.len() {
-                            return Err(Error::InvalidSectionArgument);
-                        }
-
-                        if current_stack_height + types[section as usize].max_stack_height as u16 > 1024 {
-                            return Err(Error::StackOverflow);
-                        }
+
+                if target.is_returning() {
+                    // Tail calls into a returning function return on behalf of this one.
+                    if !type_entry.is_returning() {
+                        return Err(at(Error::InvalidNonReturningFlag, i));
                     }
-                    "RJUMP" | "RJUMPI" => {
-                        let offset: [u8; 2] = code[i + 1..i + 3].try_into().unwrap();
-                        let offset = i16::from_be_bytes(offset);
-                        let dest = (i + 1 + op.immediates as usize) as i32 + offset as i32;
-
-                        if dest as usize >= code.len() {
-                            return Err(Error::InvalidJumpdest);
-                        }
-                        rjumpdests.insert(dest as u16);
-
-                        if op.name == "RJUMPI" {
-                            worklist.insert(dest as u16, (current_stack_height, visiting));
-                        }
+                    if type_entry.outputs < target.outputs {
+                        return Err(at(Error::JumpfDestinationIncompatibleOutputs, i));
                     }
-                    "RJUMPV" => {
-                        let count = code[i + 1];
-                        if count == 0 {
-                            return Err(Error::InvalidBranchCount);
-                        }
-                        // Add immediates
-                        let imm_pc: Vec<u16> = ((i + 2) as u16..((i + 2 + (count * 2) as usize) as u16))
-                            .collect();
-                        let imm: HashSet<u16> = HashSet::from_iter(imm_pc.iter().cloned());
-                        immediates = immediates.union(&imm).cloned().collect();
-
-                        let inst_end = i + 1 + op.immediates as usize + (count as usize * 2);
-
-                        rjumpdests.insert(inst_end as u16);
-                        worklist.insert(inst_end as u16, (current_stack_height, visiting));
-                        for j in 0..(count as usize) {
-                            let offset: [u8; 2] =
-                                code[i + 2 + (j * 2)..i + 4 + (j * 2)].try_into().unwrap();
-                            let offset = i16::from_be_bytes(offset);
-                            let dest = inst_end as i32 + offset as i32;
-                            if dest as usize >= code.len() {
-                                return Err(Error::InvalidJumpdest);
-                            }
-                            rjumpdests.insert(dest as u16);
-                            worklist.insert(dest as u16, (current_stack_height, visiting));
-                        }
-                        i += count as usize * 2;
+
+                    let required = type_entry.outputs as u16 + target.inputs as u16 -
+                        target.outputs as u16;
+                    if stack_min < required {
+                        return Err(at(
+                            Error::StackUnderflow {
+                                required,
+                                actual: stack_min,
+                            },
+                            i,
+                        ));
                     }
-                    "RETF" => {
-                        if current_stack_height != types[function_id].outputs as u16 {
-                            return Err(Error::InvalidOutputs);
-                        }
+                    if stack_max > required {
+                        return Err(at(
+                            Error::InvalidStackHeight {
+                                expected: required,
+                                actual: stack_max,
+                            },
+                            i,
+                        ));
                     }
-                    _ => {}
-                }
-                let imm_pc: Vec<u16> = ((i + 1) as u16..((i + 1 + op.immediates as usize) as u16))
-                    .collect();
-                let imm: HashSet<u16> = HashSet::from_iter(imm_pc.iter().cloned());
-                immediates = immediates.union(&imm).cloned().collect();
-                i += 1 + op.immediates as usize;
-
-                if op.is_terminating || op.name == "RJUMP" {
-                    ends_with_terminating_instruction = true;
-                    visiting = false;
-                } else {
-                    ends_with_terminating_instruction = false;
+                    returns = true;
+                } else if stack_min < target.inputs as u16 {
+                    return Err(at(
+                        Error::StackUnderflow {
+                            required: target.inputs as u16,
+                            actual: stack_min,
+                        },
+                        i,
+                    ));
                 }
             }
-            Err(_) => {
-                return Err(Error::UndefinedInstruction(code[i]));
+            _ => {}
+        }
+
+        let next_range = (
+            stack_min - stack_inputs + stack_outputs,
+            stack_max - stack_inputs + stack_outputs,
+        );
+        if next_range.1 > 1024 {
+            return Err(at(Error::StackOverflow, i));
+        }
+        max_stack_height = max_stack_height.max(next_range.1);
+
+        // Check every jump destination before recording the edges of this instruction.
+        let next = i + size;
+        let jump_table = match op.name {
+            "RJUMP" | "RJUMPI" => i + 1..i + 3,
+            "RJUMPV" => i + 2..next,
+            _ => next..next,
+        };
+        for offset in jump_table.clone().step_by(2) {
+            relative_jump_target(code, offset, next, &instruction_starts)
+                .ok_or_else(|| at(Error::InvalidJumpdest, i))?;
+        }
+
+        if !op.is_terminating && op.name != "RJUMP" {
+            if next >= code.len() {
+                return Err(at(Error::InvalidCodeTermination, i));
+            }
+            record_edge(&mut stack_heights, i, next, next_range)
+                .map_err(|error| at(error, next))?;
+        }
+        for offset in jump_table.step_by(2) {
+            if let Some(target) = relative_jump_target(code, offset, next, &instruction_starts) {
+                record_edge(&mut stack_heights, i, target, next_range)
+                    .map_err(|error| at(error, target))?;
             }
         }
+
+        i = next;
     }
 
-    if !immediates.is_disjoint(&rjumpdests) {
-        return Err(Error::InvalidJumpdest);
+    Ok((max_stack_height, returns, references))
+}
+
+/// Stack height range of an offset no edge has reached yet. Real heights never exceed 1024.
+const UNVISITED: (u16, u16) = (u16::MAX, u16::MAX);
+
+/// Records the stack height range flowing from the instruction at `from` into `target`.
+fn record_edge(
+    stack_heights: &mut [(u16, u16)],
+    from: usize,
+    target: usize,
+    range: (u16, u16),
+) -> Result<()> {
+    let (min, max) = stack_heights[target];
+    if target > from {
+        // Forward edges widen the range recorded at the target.
+        stack_heights[target] = if (min, max) == UNVISITED {
+            range
+        } else {
+            (min.min(range.0), max.max(range.1))
+        };
+        Ok(())
+    } else if (min, max) == range {
+        // Backward edges must agree with what the forward pass already recorded.
+        Ok(())
+    } else {
+        let (expected, actual) = if min != range.0 { (min, range.0) } else { (max, range.1) };
+        Err(Error::ConflictingStack { expected, actual })
     }
+}
 
-    // check elements in worklist for stack height
-    for (pc, (stack_height, _)) in worklist.iter() {
-        if let Some(sh) = stack_heights.get(pc) {
-            if sh != stack_height {
-                return Err(Error::ConflictingStack);
-            }
-        }
+/// Size of the instruction at `offset`, including its immediates and RJUMPV jump table.
+fn instruction_size(code: &[u8], offset: usize, op: &OpCode, spec: Spec) -> usize {
+    let mut size = 1 + op.immediates as usize;
+    if op.name == "RJUMPV" && offset + 1 < code.len() {
+        size += rjumpv_count(code[offset + 1], spec) * 2;
+    }
+    size
+}
+
+/// A fixed size set of code offsets.
+struct Bitmap {
+    words: Vec<u64>,
+}
+
+impl Bitmap {
+    fn new(len: usize) -> Self {
+        Bitmap { words: vec![0; len.div_ceil(64)] }
     }
 
-    if max_stack_height != types[function_id].max_stack_height as u16 {
-        return Err(Error::InvalidMaxStackHeight);
+    fn set(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
     }
 
-    if !ends_with_terminating_instruction {
-        return Err(Error::InvalidCodeTermination);
+    /// Whether `index` is in the set. Offsets past the end never are.
+    fn get(&self, index: usize) -> bool {
+        self.words.get(index / 64).is_some_and(|word| word & (1 << (index % 64)) != 0)
     }
+}
 
-    i = 0;
-    loop {
-        if i >= code.len() {
-            break;
+/// Computes the `max_stack_height` a code section's type entry must declare.
+///
+/// Declared heights in `types` are only used for the stack overflow check of CALLF, and the
+/// rules of the container kind are not checked.
+pub fn max_stack_height(
+    function_id: usize,
+    code: &[u8],
+    types: &[EOFTypeSectionEntry],
+    data_size: usize,
+    container_count: usize,
+    spec: Spec,
+) -> Result<u16> {
+    analyze_code(function_id, code, types, data_size, container_count, None, spec)
+        .map(|(max_stack_height, _, _)| max_stack_height)
+}
+
+/// Computes the `max_stack_height` of every code section of a container.
+pub fn max_stack_heights(container: &EOFContainer) -> Result<Vec<u16>> {
+    max_stack_heights_with_spec(container, Spec::default())
+}
+
+pub fn max_stack_heights_with_spec(container: &EOFContainer, spec: Spec) -> Result<Vec<u16>> {
+    let mut types = None;
+    let mut codes = vec![];
+    let mut data_size = None;
+    let mut container_count = 0;
+    for section in &container.sections {
+        match section {
+            EOFSection::Type(entries) if types.is_none() => types = Some(entries.clone()),
+            EOFSection::Code(code) => codes.push(code),
+            EOFSection::Data(data) if data_size.is_none() => {
+                data_size = Some(data.declared_size as usize)
+            }
+            EOFSection::Container(_) => container_count += 1,
+            _ => {}
         }
-        if worklist.contains_key(&(i as u16)) {
-            match worklist.get(&(i as u16)) {
-                Some((_, v)) => visiting = *v,
-                None => visiting = false,
+    }
+    let mut types = types.ok_or(Error::MissingTypeHeader)?;
+    if types.len() != codes.len() {
+        return Err(Error::InvalidCodeHeader);
+    }
+
+    // Outdated declared heights must not trip the CALLF overflow check.
+    for entry in &mut types {
+        entry.max_stack_height = 0;
+    }
+    codes
+        .iter()
+        .enumerate()
+        .map(|(i, code)| {
+            max_stack_height(i, code, &types, data_size.unwrap_or(0), container_count, spec)
+        })
+        .collect()
+}
+
+/// Rewrites the `max_stack_height` of every type entry, including those of subcontainers, to
+/// the computed value.
+pub fn fix_stack_heights(container: &mut EOFContainer) -> Result<()> {
+    fix_stack_heights_with_spec(container, Spec::default())
+}
+
+pub fn fix_stack_heights_with_spec(container: &mut EOFContainer, spec: Spec) -> Result<()> {
+    let heights = max_stack_heights_with_spec(container, spec)?;
+    let mut fixed = false;
+    for section in &mut container.sections {
+        match section {
+            EOFSection::Type(types) if !fixed => {
+                for (entry, height) in types.iter_mut().zip(&heights) {
+                    entry.max_stack_height = *height;
+                }
+                fixed = true;
             }
+            EOFSection::Container(subcontainer) if spec.supports_container_sections() => {
+                fix_stack_heights_with_spec(subcontainer, spec)?;
+            }
+            _ => {}
         }
+    }
+    Ok(())
+}
+
+/// Resolves the relative jump offset stored at `offset` against `base`, returning the target if
+/// it is the start of an instruction.
+fn relative_jump_target(
+    code: &[u8],
+    offset: usize,
+    base: usize,
+    instruction_starts: &Bitmap,
+) -> Option<usize> {
+    let relative = i16::from_be_bytes([code[offset], code[offset + 1]]);
+    let target = usize::try_from(base as isize + relative as isize).ok()?;
+    instruction_starts.get(target).then_some(target)
+}
+
+/// Number of RJUMPV jump table entries encoded by its immediate.
+pub(crate) fn rjumpv_count(immediate: u8, spec: Spec) -> usize {
+    if spec.rjumpv_max_index() {
+        immediate as usize + 1
+    } else {
+        immediate as usize
+    }
+}
+
+/// How serious a validation finding is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The container is invalid under the spec.
+    Error,
+    /// The container is valid but likely not what was intended.
+    Warning,
+}
+
+/// A single finding reported by `EOFValidator::validate_all`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub error: Error,
+}
 
-        let op = OpCode::from(code[i]).unwrap();
-        if visiting == false {
-            return Err(Error::UnreachableCode);
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "error: {}", self.error),
+            Severity::Warning => write!(f, "warning: {}", self.error),
         }
+    }
+}
+
+/// Collects diagnostics, or stops at the first error when not collecting.
+struct Report {
+    collect: bool,
+    diagnostics: Vec<Diagnostic>,
+}
 
-        if op.is_terminating || op.name == "RJUMPI" || op.name == "RJUMPV" {
-            visiting = false;
+impl Report {
+    fn error(&mut self, error: Error) -> Result<()> {
+        if !self.collect {
+            return Err(error);
         }
+        self.diagnostics.push(Diagnostic { severity: Severity::Error, error });
+        Ok(())
+    }
 
-        if op.name == "RJUMPV" {
-            let count = code[i + 1];
-            i += 1 + op.immediates as usize + (count as usize * 2);
-        } else {
-            i += 1 + op.immediates as usize;
+    fn warning(&mut self, error: Error) {
+        if self.collect {
+            self.diagnostics.push(Diagnostic { severity: Severity::Warning, error });
         }
     }
 
-    Ok(())
+    fn error_count(&self) -> usize {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Error).count()
+    }
 }
 
+/// Validates containers as runtime code unless a `ContainerKind` is given.
 pub trait EOFValidator {
-    fn is_valid_eof(&self) -> Result<()>;
+    fn is_valid_eof(&self) -> Result<()> {
+        self.is_valid_eof_with_spec(Spec::default())
+    }
+
+    fn is_valid_eof_with_spec(&self, spec: Spec) -> Result<()> {
+        self.is_valid_eof_with_kind(spec, ContainerKind::Runtime)
+    }
+
+    fn is_valid_eof_with_kind(&self, spec: Spec, kind: ContainerKind) -> Result<()>;
+
+    /// Runs every check and returns all findings instead of stopping at the first error.
+    fn validate_all(&self) -> Vec<Diagnostic> {
+        self.validate_all_with_spec(Spec::default())
+    }
+
+    fn validate_all_with_spec(&self, spec: Spec) -> Vec<Diagnostic> {
+        self.validate_all_with_kind(spec, ContainerKind::Runtime)
+    }
+
+    fn validate_all_with_kind(&self, spec: Spec, kind: ContainerKind) -> Vec<Diagnostic>;
 }
 
 impl EOFValidator for EOFContainer {
-    fn is_valid_eof(&self) -> Result<()> {
-        if self.version != EOF_VERSION_1 {
-            return Err(Error::UnsupportedVersion);
-        }
+    fn is_valid_eof_with_kind(&self, spec: Spec, kind: ContainerKind) -> Result<()> {
+        let mut report = Report { collect: false, diagnostics: Vec::new() };
+        validate_container(self, spec, kind, 0, &mut report)
+    }
 
-        if self.sections.is_empty() {
-            return Err(Error::NoSections);
+    fn validate_all_with_kind(&self, spec: Spec, kind: ContainerKind) -> Vec<Diagnostic> {
+        let mut report = Report { collect: true, diagnostics: Vec::new() };
+        if let Err(error) = validate_container(self, spec, kind, 0, &mut report) {
+            // Errors returned directly are the ones that prevent further checks.
+            report.diagnostics.push(Diagnostic { severity: Severity::Error, error });
         }
+        report.diagnostics
+    }
+}
 
-        let mut code_count = 0;
-        let mut data_found = false;
-        let mut type_found: Option<usize> = None;
-        let mut last_section_priority = 0u8;
+fn validate_container(
+    container: &EOFContainer,
+    spec: Spec,
+    kind: ContainerKind,
+    depth: usize,
+    report: &mut Report,
+) -> Result<()> {
+    if container.version != EOF_VERSION_1 {
+        return Err(Error::UnsupportedVersion);
+    }
 
-        let mut error = None;
-        for i in 0..self.sections.len() {
-            let current_priority = self.sections[i].priority();
-            if last_section_priority > current_priority {
-                error = Some(Error::InvalidSectionOrder);
-            }
-            last_section_priority = self.sections[i].priority();
+    if container.sections.is_empty() {
+        return Err(Error::NoSections);
+    }
 
-            match &self.sections[i] {
-                EOFSection::Type(_) => {
-                    if type_found.is_some() {
-                        return Err(Error::DuplicateTypeSection);
-                    }
-                    type_found = Some(i);
+    // The report is shared with the parent, so only errors from here on concern this container.
+    let errors_before = report.error_count();
+
+    let mut code_count = 0;
+    let mut container_count = 0;
+    let mut data_size = 0;
+    let mut data_found = false;
+    let mut type_found: Option<&Vec<EOFTypeSectionEntry>> = None;
+    let mut last_section_priority = 0u8;
+
+    for section in &container.sections {
+        if last_section_priority > section.priority() {
+            report.error(Error::InvalidSectionOrder)?;
+        }
+        last_section_priority = section.priority();
+
+        match section {
+            EOFSection::Type(types) => {
+                if type_found.is_some() {
+                    report.error(Error::DuplicateTypeSection)?;
+                } else {
+                    type_found = Some(types);
                 }
-                EOFSection::Code(c) => {
-                    if c.len() == 0 {
-                        return Err(Error::InvalidCodeSize);
-                    }
-                    code_count += 1;
+            }
+            EOFSection::Code(c) => {
+                if c.is_empty() {
+                    report.error(Error::InvalidCodeSize.at(code_count, None, None))?;
+                }
+                code_count += 1;
+            }
+            EOFSection::Data(d) => {
+                if data_found {
+                    report.error(Error::DuplicateDataSection)?;
+                    continue;
+                }
+                // DATALOADN bounds are checked against the declared size.
+                data_size = d.declared_size as usize;
+                data_found = true;
+                if d.data.len() > u16::MAX as usize {
+                    let kind = spec.data_section_kind();
+                    report.error(Error::SectionTooLarge { kind, size: d.data.len(), spec })?;
+                } else if d.data.len() > d.declared_size as usize {
+                    report.error(Error::InvalidDataSize)?;
                 }
-                EOFSection::Data(_) => {
-                    data_found = true;
+                // Only subcontainers deployed by RETURNCODE are completed with auxdata.
+                if d.is_truncated() && (depth == 0 || kind != ContainerKind::Runtime) {
+                    report.error(Error::TruncatedDataSection)?;
                 }
             }
+            EOFSection::Container(_) => {
+                if !spec.supports_container_sections() {
+                    report.error(Error::UnsupportedSectionKind)?;
+                }
+                container_count += 1;
+            }
         }
+    }
 
-        if !type_found.is_some() {
-            return Err(Error::MissingTypeHeader);
-        }
+    let types = type_found.ok_or(Error::MissingTypeHeader)?;
 
-        if code_count == 0 {
-            return Err(Error::MissingCodeHeader);
-        }
+    if code_count == 0 {
+        return Err(Error::MissingCodeHeader);
+    }
 
-        if !data_found {
-            return Err(Error::MissingDataHeader);
-        }
+    if !data_found {
+        report.error(Error::MissingDataHeader)?;
+    }
 
-        if let Some(err) = error {
-            return Err(err);
-        }
+    if types.len() != code_count {
+        return Err(Error::InvalidCodeHeader);
+    }
 
-        let type_found = type_found.unwrap();
-        if let EOFSection::Type(ref types) = self.sections[type_found] {
-            if types.len() != code_count {
-                return Err(Error::InvalidCodeHeader);
-            }
+    // Validate max inputs, outputs and stack height
+    for (i, type_entry) in types.iter().enumerate() {
+        if type_entry.inputs > 127 {
+            report.error(Error::TooManyInputs.at(i, None, None))?;
+        }
+        if type_entry.outputs > 127 &&
+            (type_entry.is_returning() || !spec.supports_non_returning_functions())
+        {
+            report.error(Error::TooManyOutputs.at(i, None, None))?;
+        }
+        if type_entry.max_stack_height >= 1024 {
+            report.error(Error::TooLargeMaxStackHeight.at(i, None, None))?;
+        }
+        // Section 0 takes no inputs and is non-returning where the spec allows it.
+        let section_0_outputs = if spec.supports_non_returning_functions() {
+            EOF_NON_RETURNING_FUNCTION
+        } else {
+            0
+        };
+        if i == 0 && (type_entry.inputs != 0 || type_entry.outputs != section_0_outputs) {
+            report.error(Error::InvalidSection0Type.at(i, None, None))?;
+        }
+    }
 
-            // Validate max inputs, outputs and stack height
-            for i in 0..types.len() {
-                if types[i].inputs > 127 {
-                    return Err(Error::TooManyInputs);
-                }
-                if types[i].outputs > 127 {
-                    return Err(Error::TooManyOutputs);
-                }
-                if types[i].max_stack_height >= 1024 {
-                    return Err(Error::TooLargeMaxStackHeight);
+    // Iterate over code sections and validate each one.
+    let codes: Vec<&Vec<u8>> = container
+        .sections
+        .iter()
+        .filter_map(|section| match section {
+            EOFSection::Code(code) => Some(code),
+            _ => None,
+        })
+        .collect();
+    // Whether each subcontainer is referenced as initcode (by EOFCREATE) and as runtime code (by
+    // RETURNCODE).
+    let mut referenced = vec![(false, false); container_count];
+    for (i, code) in codes.iter().enumerate() {
+        if code.is_empty() {
+            continue;
+        }
+        match validate_code(i, code, types, data_size, container_count, kind, spec) {
+            Ok(references) => {
+                for (index, required) in references {
+                    match required {
+                        ContainerKind::Initcode => referenced[index].0 = true,
+                        ContainerKind::Runtime => referenced[index].1 = true,
+                    }
                 }
-                if i == 0 && (types[i].inputs != 0 || types[i].outputs != 0) {
-                    return Err(Error::InvalidSection0Type);
+            }
+            Err(error) => report.error(error)?,
+        }
+    }
+    let code_valid = report.error_count() == errors_before;
+
+    // Functions that can never be entered are only reported once the code itself is valid. The
+    // draft specs allowed them, so there they are only a warning.
+    if code_valid {
+        let mut reachable = vec![false; codes.len()];
+        let mut worklist = vec![0];
+        reachable[0] = true;
+        while let Some(i) = worklist.pop() {
+            for target in section_references(codes[i], spec) {
+                if !reachable[target] {
+                    reachable[target] = true;
+                    worklist.push(target);
                 }
             }
+        }
+        for (i, _) in reachable.iter().enumerate().filter(|(_, r)| !**r) {
+            let error = Error::UnreachableCodeSection.at(i, None, None);
+            if spec.requires_reachable_code_sections() {
+                report.error(error)?;
+            } else {
+                report.warning(error);
+            }
+        }
+    }
 
-            // Iterate over code sections and validate each one.
-            let mut code_sections_count = 0;
-            for i in 0..self.sections.len() {
-                if let EOFSection::Code(ref code) = self.sections[i] {
-                    validate_code(code_sections_count, code, types)?;
-                    code_sections_count += 1;
+    // Each subcontainer is validated as the kind of container its references require. Without
+    // valid code, missing references are expected and not reported.
+    if spec.supports_container_sections() {
+        let subcontainers = container.sections.iter().filter_map(|section| match section {
+            EOFSection::Container(subcontainer) => Some(subcontainer),
+            _ => None,
+        });
+        for (index, subcontainer) in subcontainers.enumerate() {
+            let kind = match referenced[index] {
+                (true, false) => ContainerKind::Initcode,
+                (false, true) => ContainerKind::Runtime,
+                (true, true) => {
+                    report.error(Error::AmbiguousContainerKind { index })?;
+                    continue;
+                }
+                (false, false) => {
+                    if code_valid {
+                        report.error(Error::OrphanSubcontainer { index })?;
+                    }
+                    continue;
                 }
+            };
+            if depth >= EOF_MAX_CONTAINER_DEPTH {
+                return Err(Error::ContainerTooDeep);
+            }
+            if let Err(error) = validate_container(subcontainer, spec, kind, depth + 1, report) {
+                report.error(error)?;
             }
+        }
+    }
+    Ok(())
+}
 
-            let types_count = types.len();
-            if code_sections_count != types_count {
-                return Err(Error::InvalidCodeHeader);
+/// Code sections targeted by CALLF or JUMPF in a code section that passed validation.
+fn section_references(code: &[u8], spec: Spec) -> Vec<usize> {
+    let mut targets = Vec::new();
+    let mut i = 0;
+    while i < code.len() {
+        let Ok(op) = OpCode::from(code[i], spec) else {
+            break;
+        };
+        match op.name {
+            "CALLF" | "JUMPF" => {
+                targets.push(u16::from_be_bytes([code[i + 1], code[i + 2]]) as usize);
             }
-        } else {
-            panic!(); // In case the above logic is wrong.
+            "RJUMPV" => i += rjumpv_count(code[i + 1], spec) * 2,
+            _ => {}
         }
-        Ok(())
+        i += 1 + op.immediates as usize;
     }
+    targets
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::asm::assemble;
+    use super::super::builder::EOFBuilder;
+    use super::super::ser::to_bytes;
     use super::super::de::*;
+    use super::super::error::Location;
 
     #[test]
     fn complex_container() {
@@ -293,42 +779,43 @@ mod tests {
                 EOFSection::Type(vec![
                     EOFTypeSectionEntry {
                         inputs: 0,
-                        outputs: 0,
+                        outputs: 0x80,
                         max_stack_height: 0,
                     },
                     EOFTypeSectionEntry {
                         inputs: 0,
-                        outputs: 0,
+                        outputs: 0x80,
                         max_stack_height: 0,
                     },
                 ]),
+                // JUMPF 1
+                EOFSection::Code(vec![0xe5, 0x00, 0x01]),
                 EOFSection::Code(vec![0xfe]),
-                EOFSection::Code(vec![0xfe]),
-                EOFSection::Data(vec![0, 1, 2, 3, 4]),
+                EOFSection::Data(vec![0, 1, 2, 3, 4].into()),
             ],
         };
 
-        assert_eq!(container.is_valid_eof().is_ok(), true);
+        assert!(container.is_valid_eof().is_ok());
     }
 
     #[test]
-    fn valid_data_container() {
+    fn duplicate_data_sections() {
         let container = EOFContainer {
             version: 1,
             sections: vec![
                 EOFSection::Type(vec![
                     EOFTypeSectionEntry {
                         inputs: 0,
-                        outputs: 0,
+                        outputs: 0x80,
                         max_stack_height: 0,
                     },
                 ]),
                 EOFSection::Code(vec![0xfe]),
-                EOFSection::Data(vec![0, 1, 2, 3, 4]),
-                EOFSection::Data(vec![0, 1, 2, 3, 4]),
+                EOFSection::Data(vec![0, 1, 2, 3, 4].into()),
+                EOFSection::Data(vec![0, 1, 2, 3, 4].into()),
             ],
         };
-        assert!(container.is_valid_eof().is_ok());
+        assert_eq!(container.is_valid_eof(), Err(Error::DuplicateDataSection));
     }
 
     #[test]
@@ -337,11 +824,11 @@ mod tests {
             version: 2,
             sections: vec![
                 EOFSection::Code(vec![0xfe]),
-                EOFSection::Data(vec![0, 1, 2, 3, 4]),
+                EOFSection::Data(vec![0, 1, 2, 3, 4].into()),
             ],
         };
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::UnsupportedVersion)
         );
     }
@@ -352,7 +839,10 @@ mod tests {
             version: 1,
             sections: vec![],
         };
-        assert_eq!(container.is_valid_eof().err(), Some(Error::NoSections));
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::NoSections)
+        );
     }
 
     #[test]
@@ -367,11 +857,11 @@ mod tests {
                         max_stack_height: 0,
                     },
                 ]),
-                EOFSection::Data(vec![0xfe]),
+                EOFSection::Data(vec![0xfe].into()),
             ],
         };
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::MissingCodeHeader)
         );
     }
@@ -389,11 +879,11 @@ mod tests {
                         max_stack_height: 0,
                     },
                 ]),
-                EOFSection::Data(vec![]),
+                EOFSection::Data(vec![].into()),
             ],
         };
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::InvalidSectionOrder)
         );
     }
@@ -403,7 +893,7 @@ mod tests {
         let container = EOFContainer {
             version: 1,
             sections: vec![
-                EOFSection::Data(vec![0, 1, 2, 3, 4]),
+                EOFSection::Data(vec![0, 1, 2, 3, 4].into()),
                 EOFSection::Type(vec![
                     EOFTypeSectionEntry {
                         inputs: 0,
@@ -415,7 +905,7 @@ mod tests {
             ],
         };
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::InvalidSectionOrder)
         );
     }
@@ -432,12 +922,12 @@ mod tests {
                         max_stack_height: 0,
                     },
                 ]),
-                EOFSection::Data(vec![0, 1, 2, 3, 4]),
+                EOFSection::Data(vec![0, 1, 2, 3, 4].into()),
                 EOFSection::Code(vec![0xfe]),
             ],
         };
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::InvalidSectionOrder)
         );
     }
@@ -455,11 +945,11 @@ mod tests {
                         max_stack_height: 0,
                     },
                 ]),
-                EOFSection::Data(vec![]),
+                EOFSection::Data(vec![].into()),
             ],
         };
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::InvalidSectionOrder)
         );
     }
@@ -487,7 +977,7 @@ mod tests {
             ],
         };
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::DuplicateTypeSection)
         );
     }
@@ -510,11 +1000,11 @@ mod tests {
                     },
                 ]),
                 EOFSection::Code(vec![0xfe]),
-                EOFSection::Data(vec![]),
+                EOFSection::Data(vec![].into()),
             ],
         };
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::InvalidCodeHeader)
         );
     }
@@ -533,11 +1023,11 @@ mod tests {
                 ]),
                 EOFSection::Code(vec![0xfe]),
                 EOFSection::Code(vec![0xfe]),
-                EOFSection::Data(vec![]),
+                EOFSection::Data(vec![].into()),
             ],
         };
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::InvalidCodeHeader)
         );
 
@@ -557,7 +1047,7 @@ mod tests {
         };
 
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::MissingTypeHeader)
         );
 
@@ -565,13 +1055,13 @@ mod tests {
             version: 1,
             sections: vec![
                 EOFSection::Code(vec![0xfe]),
-                EOFSection::Data(vec![0xaa, 0xbb]),
+                EOFSection::Data(vec![0xaa, 0xbb].into()),
             ],
         };
 
 
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::MissingTypeHeader)
         );
 
@@ -589,23 +1079,23 @@ mod tests {
                         max_stack_height: 0,
                     },
                 ]),
-                EOFSection::Data(vec![0xaa, 0xbb]),
+                EOFSection::Data(vec![0xaa, 0xbb].into()),
             ],
         };
 
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::MissingCodeHeader)
         );
     }
 
     #[test]
     fn invalid_code_header() {
-        let code = hex::decode("ef00010100080200010001030000000000000000000000fe").unwrap();
+        let code = hex::decode("ef00010100080200010001040000000080000000000000fe").unwrap();
         let container = from_slice(&code).unwrap();
 
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::InvalidCodeHeader)
         );
     }
@@ -627,7 +1117,7 @@ mod tests {
         };
 
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::MissingDataHeader)
         );
     }
@@ -635,31 +1125,37 @@ mod tests {
     #[test]
     fn too_many_inputs() {
         for i in 128..256 {
-            let code = hex::decode(format!("ef0001010004020001000103000000{:02x}000000fe", i))
+            let code = hex::decode(format!("ef0001010004020001000104000000{:02x}000000fe", i))
                 .unwrap();
             let container = from_slice(&code).unwrap();
 
-            assert_eq!(container.is_valid_eof().err(), Some(Error::TooManyInputs));
+            assert_eq!(
+                container.is_valid_eof().map_err(Error::into_kind).err(),
+                Some(Error::TooManyInputs)
+            );
         }
     }
 
     #[test]
     fn too_many_outputs() {
-        let code = hex::decode("ef000101000402000100010300000000ff0000fe").unwrap();
+        let code = hex::decode("ef000101000402000100010400000000ff0000fe").unwrap();
         let container = from_slice(&code).unwrap();
 
-        assert_eq!(container.is_valid_eof().err(), Some(Error::TooManyOutputs));
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::TooManyOutputs)
+        );
     }
 
     #[test]
     fn too_large_max_stack_height() {
         for i in 1024..65536 {
-            let code = hex::decode(format!("ef00010100040200010001030000000000{:04x}fe", i))
+            let code = hex::decode(format!("ef00010100040200010001040000000000{:04x}fe", i))
                 .unwrap();
             let container = from_slice(&code).unwrap();
 
             assert_eq!(
-                container.is_valid_eof().err(),
+                container.is_valid_eof().map_err(Error::into_kind).err(),
                 Some(Error::TooLargeMaxStackHeight)
             );
         }
@@ -667,31 +1163,130 @@ mod tests {
 
     #[test]
     fn invalid_section_0_type() {
-        let code = hex::decode("ef000101000402000100010300000001000000fe").unwrap();
+        let code = hex::decode("ef000101000402000100010400000001000000fe").unwrap();
         let container = from_slice(&code).unwrap();
 
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::InvalidSection0Type)
         );
 
-        let code = hex::decode("ef000101000402000100010300000000010000fe").unwrap();
+        let code = hex::decode("ef000101000402000100010400000000010000fe").unwrap();
         let container = from_slice(&code).unwrap();
 
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidSection0Type)
+        );
+    }
+
+    #[test]
+    fn non_returning_functions() {
+        // Section 0 must be non-returning
+        let code = hex::decode("ef000101000402000100010400000000000000fe").unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::InvalidSection0Type)
         );
+        assert!(container.is_valid_eof_with_spec(Spec::ShanghaiDraft).is_ok());
+
+        // RETF in a non-returning function
+        let code = hex::decode("ef00010100080200020001000104000000008000000080000000e4").unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidNonReturningFlag)
+        );
+
+        // Returning function without RETF
+        let code = hex::decode("ef0001010008020002000100010400000000800000000000000000").unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidNonReturningFlag)
+        );
+
+        // CALLF to a non-returning function
+        let code = hex::decode("ef000101000802000200040001040000000080000000800000e300010000")
+            .unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::CallfToNonReturningFunction)
+        );
+
+        // JUMPF from a function with 0 outputs to one with 1 output
+        let code = hex::decode(
+            "ef000101000c0200030001000300020400000000800000000000000001000100e500025fe4",
+        ).unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::JumpfDestinationIncompatibleOutputs)
+        );
+    }
+
+    #[test]
+    fn jumpf() {
+        // JUMPF to a non-returning function terminates the section
+        let code = hex::decode("ef000101000802000200030001040000000080000000800000e5000100")
+            .unwrap();
+        let container = from_slice(&code).unwrap();
+        assert!(container.is_valid_eof().is_ok());
+
+        // JUMPF to a returning function with matching outputs
+        let code = hex::decode(
+            "ef000101000c02000300040003000104000000008000000000000000000000e3000100e50002e4",
+        ).unwrap();
+        let container = from_slice(&code).unwrap();
+        assert!(container.is_valid_eof().is_ok());
+
+        // Code after JUMPF is unreachable
+        let code = hex::decode("ef000101000802000200040001040000000080000000800000e500010000")
+            .unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::UnreachableCode)
+        );
+
+        // Target out of bounds
+        let code = hex::decode("ef000101000402000100030400000000800000e50005").unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidSectionArgument)
+        );
+
+        // Extra stack item when tail calling a returning function
+        let code = hex::decode(
+            "ef000101000c02000300040004000104000000008000000000000100000000e30001005fe50002e4",
+        ).unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidStackHeight { expected: 0, actual: 1 })
+        );
+
+        // Not enough inputs for the target
+        let code = hex::decode("ef000101000802000200030002040000000080000001800001e500015000")
+            .unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::StackUnderflow { required: 1, actual: 0 })
+        );
     }
 
     #[test]
     fn invalid_code_size() {
-        let code = hex::decode("ef00010100040200000300000000000000").unwrap();
+        let code = hex::decode("ef00010100040200000400000000000000").unwrap();
         let container = from_slice(&code);
 
         assert_eq!(container.err(), Some(Error::InvalidCodeSize));
 
-        let code = hex::decode("ef000101000402000100000300000000000000").unwrap();
+        let code = hex::decode("ef000101000402000100000400000000000000").unwrap();
         let container = from_slice(&code[..]);
 
         assert_eq!(container.err(), Some(Error::InvalidCodeSize));
@@ -701,40 +1296,48 @@ mod tests {
 
     #[test]
     fn undefined_instruction() {
-        let code = hex::decode("ef00010100040200010001030000000000000056").unwrap();
+        let code = hex::decode("ef00010100040200010001040000000080000056").unwrap();
         let container = from_slice(&code).unwrap();
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::UndefinedInstruction(0x56))
         );
 
-        let code = hex::decode("ef000101000402000100010300000000000000b3").unwrap();
+        let code = hex::decode("ef000101000402000100010400000000800000b3").unwrap();
         let container = from_slice(&code).unwrap();
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::UndefinedInstruction(0xb3))
         );
 
+        // Legacy calls are not available in EOF
+        let code = hex::decode("ef000101000402000100010400000000800000f1").unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::UndefinedInstruction(0xf1))
+        );
+
     }
 
     #[test]
     fn truncated_immediate() {
-        let code = hex::decode("ef00010100040200010001030000000000000160").unwrap();
+        let code = hex::decode("ef00010100040200010001040000000080000160").unwrap();
         let container = from_slice(&code).unwrap();
 
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::TruncatedImmediate)
         );
     }
 
     #[test]
     fn invalid_section_argument() {
-        let code = hex::decode("ef000101000402000100040300000000000000b0000100").unwrap();
+        let code = hex::decode("ef000101000402000100040400000000800000e3000100").unwrap();
         let container = from_slice(&code).unwrap();
 
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::InvalidSectionArgument)
         );
     }
@@ -742,66 +1345,111 @@ mod tests {
     #[test]
     fn invalid_jumpdest() {
         // Target header
-        let code = hex::decode("ef0001010004020001000303000000000000005cfffb").unwrap();
+        let code = hex::decode("ef000101000402000100030400000000800000e0fffb").unwrap();
         let container = from_slice(&code).unwrap();
 
-        assert_eq!(container.is_valid_eof().err(), Some(Error::InvalidJumpdest));
-
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidJumpdest)
+        );
+
         // Target before container code
-        let code = hex::decode("ef0001010004020001000303000000000000005cffe9").unwrap();
+        let code = hex::decode("ef000101000402000100030400000000800000e0ffe9").unwrap();
         let container = from_slice(&code).unwrap();
 
-        assert_eq!(container.is_valid_eof().err(), Some(Error::InvalidJumpdest));
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidJumpdest)
+        );
 
         // Target into data section
-        let code = hex::decode("ef0001010004020001000303000400000000005c0002aabbccdd").unwrap();
+        let code = hex::decode("ef000101000402000100030400040000800000e00002aabbccdd").unwrap();
         let container = from_slice(&code).unwrap();
 
-        assert_eq!(container.is_valid_eof().err(), Some(Error::InvalidJumpdest));
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidJumpdest)
+        );
 
         // Target after code end
-        let code = hex::decode("ef0001010004020001000303000000000000005c0002").unwrap();
+        let code = hex::decode("ef000101000402000100030400000000800000e00002").unwrap();
         let container = from_slice(&code).unwrap();
 
-        assert_eq!(container.is_valid_eof().err(), Some(Error::InvalidJumpdest));
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidJumpdest)
+        );
 
         // Target immediate
         // RJUMP to self immediate:
-        let code = hex::decode("ef0001010004020001000303000000000000005cffff").unwrap();
+        let code = hex::decode("ef000101000402000100030400000000800000e0ffff").unwrap();
         let container = from_slice(&code).unwrap();
 
-        assert_eq!(container.is_valid_eof().err(), Some(Error::InvalidJumpdest));
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidJumpdest)
+        );
 
         // RJUMP to PUSH immediate
-        let code = hex::decode("ef00010100040200010007030000000000000161ffff5cfffc00").unwrap();
+        let code = hex::decode("ef00010100040200010007040000000080000161ffffe0fffc00").unwrap();
         let container = from_slice(&code).unwrap();
 
-        assert_eq!(container.is_valid_eof().err(), Some(Error::InvalidJumpdest));
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidJumpdest)
+        );
 
         // RJUMP to RJUMPI Immediate
-        let code = hex::decode("ef0001010004020001000a03000000000000005c00050060015d000000")
+        let code = hex::decode("ef0001010004020001000a0400000000800000e00005006001e1000000")
             .unwrap();
         let container = from_slice(&code).unwrap();
 
-        assert_eq!(container.is_valid_eof().err(), Some(Error::InvalidJumpdest));
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidJumpdest)
+        );
 
         // RJUMP to RJUMPV immediate
-        let code = hex::decode("ef0001010004020001001f03000000000000005c000560015e0300000006000c600160015500600260025500600360035500").unwrap();
+        let code = hex::decode("ef0001010004020001001f0400000000800000e000056001e20200000006000c600160015500600260025500600360035500").unwrap();
         let container = from_slice(&code).unwrap();
 
-        assert_eq!(container.is_valid_eof().err(), Some(Error::InvalidJumpdest));
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidJumpdest)
+        );
     }
 
     #[test]
     fn conflicting_stack() {
-        let code = hex::decode("ef0001010004020001000a030000000000000260005d00026001600200")
+        // Forward jumps merge into a stack height range
+        let code = hex::decode("ef0001010004020001000a04000000008000026000e100026001600200")
             .unwrap();
         let container = from_slice(&code).unwrap();
+        assert!(container.is_valid_eof().is_ok());
 
-        assert_eq!(
-            container.is_valid_eof().err(),
-            Some(Error::ConflictingStack)
-        );
+        // Backward jumps must match the stack height at the destination
+        let code = hex::decode("ef0001010004020001000404000000008000015fe0fffc").unwrap();
+        let container = from_slice(&code).unwrap();
+        let err = container.is_valid_eof().unwrap_err();
+        assert_eq!(err.kind(), &Error::ConflictingStack { expected: 0, actual: 1 });
+        assert_eq!(err.location().and_then(|l| l.offset), Some(0));
+
+        // A loop keeping the stack height balanced
+        let code = hex::decode("ef0001010004020001000604000000008000025f5fe1fffc00").unwrap();
+        let container = from_slice(&code).unwrap();
+        assert!(container.is_valid_eof().is_ok());
+    }
+
+    #[test]
+    fn stack_height_range() {
+        // RETF reached with a stack height of either 1 or 2
+        let code = hex::decode(
+            "ef000101000802000200040007040000000080000100010002e30001005f5fe100015fe4",
+        ).unwrap();
+        let container = from_slice(&code).unwrap();
+        let err = container.is_valid_eof().unwrap_err();
+        assert_eq!(err.kind(), &Error::InvalidOutputs { expected: 1, actual: 2 });
+        assert_eq!(err.location().and_then(|l| l.offset), Some(6));
     }
 
     #[test]
@@ -809,67 +1457,643 @@ mod tests {
         let code = hex::decode(
             "ef0001010008020002000a000603000000000000010000000260015d00030060015e006001600155b1",
         ).unwrap();
-        let container = from_slice(&code).unwrap();
+        let container = from_slice_with_spec(&code, Spec::ShanghaiDraft).unwrap();
 
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof_with_spec(Spec::ShanghaiDraft).map_err(Error::into_kind).err(),
             Some(Error::InvalidBranchCount)
         );
     }
 
+    #[test]
+    fn spec_instruction_sets() {
+        // CALL is only valid before the full EOF instruction set
+        let code = hex::decode("ef0001010004020001000904000000000000075f5f5f5f5f5f5ff100")
+            .unwrap();
+        let container = from_slice(&code).unwrap();
+        assert!(container.is_valid_eof_with_spec(Spec::ShanghaiDraft).is_ok());
+        assert!(container.is_valid_eof_with_spec(Spec::CancunDraft).is_ok());
+
+        let code = hex::decode("ef0001010004020001000904000000008000075f5f5f5f5f5f5ff100")
+            .unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof_with_spec(Spec::Prague).map_err(Error::into_kind).err(),
+            Some(Error::UndefinedInstruction(0xf1))
+        );
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::UndefinedInstruction(0xf1))
+        );
+
+        // RJUMP moved from 0x5c to 0xe0
+        let code = hex::decode("ef0001010004020001000404000000000000005c000000").unwrap();
+        let container = from_slice(&code).unwrap();
+        assert!(container.is_valid_eof_with_spec(Spec::ShanghaiDraft).is_ok());
+        assert_eq!(
+            container.is_valid_eof_with_spec(Spec::CancunDraft).map_err(Error::into_kind).err(),
+            Some(Error::StackUnderflow { required: 1, actual: 0 })
+        );
+    }
+
+    #[test]
+    fn truncated_rjumpv_table() {
+        // RJUMPV with a max index of 1 needs four bytes of jump table
+        let code = hex::decode("ef0001010004020001000504000000008000016001e20100").unwrap();
+        let container = from_slice(&code).unwrap();
+
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::TruncatedImmediate)
+        );
+    }
+
+    #[test]
+    fn maximal_rjumpv_tables() {
+        // Back to back PUSH0 RJUMPV blocks with 256 entries, all jumping to the next block
+        let types = [EOFTypeSectionEntry { inputs: 0, outputs: 0x80, max_stack_height: 1 }];
+        let mut block = vec![0x5f, 0xe2, 0xff];
+        block.resize(block.len() + 256 * 2, 0);
+        let mut code = block.repeat(40);
+        code.push(0x00);
+        let runtime = ContainerKind::Runtime;
+        assert_eq!(validate_code(0, &code, &types, 0, 0, runtime, Spec::Osaka), Ok(vec![]));
+
+        // The last entry of the first table jumps into the table itself
+        code[block.len() - 2..block.len()].copy_from_slice(&[0xff, 0xff]);
+        assert_eq!(
+            validate_code(0, &code, &types, 0, 0, runtime, Spec::Osaka).map_err(Error::into_kind),
+            Err(Error::InvalidJumpdest)
+        );
+    }
+
+    #[test]
+    fn invalid_dataloadn_index() {
+        let code = hex::decode("ef000101000402000100040400000000800001d1000000").unwrap();
+        let container = from_slice(&code).unwrap();
+
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidDataloadnIndex)
+        );
+
+        let code = hex::decode(format!(
+            "ef000101000402000100040400200000800001d1000000{}",
+            "00".repeat(32)
+        )).unwrap();
+        let container = from_slice(&code).unwrap();
+
+        assert!(container.is_valid_eof().is_ok());
+    }
+
+    #[test]
+    fn invalid_container_section_index() {
+        let code = hex::decode("ef0001010004020001000704000000008000045f5f5f5fec0000")
+            .unwrap();
+        let container = from_slice(&code).unwrap();
+
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidContainerSectionIndex)
+        );
+    }
+
+    #[test]
+    fn dupn_swapn_exchange_stack() {
+        // DUPN 1 with a single stack item
+        let code = hex::decode("ef0001010004020001000404000000008000015fe60100").unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::StackUnderflow { required: 2, actual: 1 })
+        );
+
+        // DUPN 1 with two stack items
+        let code = hex::decode("ef0001010004020001000504000000008000035f5fe60100").unwrap();
+        let container = from_slice(&code).unwrap();
+        assert!(container.is_valid_eof().is_ok());
+
+        // SWAPN 0 with a single stack item
+        let code = hex::decode("ef0001010004020001000404000000008000015fe70000").unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::StackUnderflow { required: 2, actual: 1 })
+        );
+
+        // EXCHANGE 0x00 swaps the 2nd and 3rd items
+        let code = hex::decode("ef0001010004020001000604000000008000035f5f5fe80000").unwrap();
+        let container = from_slice(&code).unwrap();
+        assert!(container.is_valid_eof().is_ok());
+    }
+
     #[test]
     fn stack_underflow() {
-        let code = hex::decode("ef00010100040200010004030000000000000160010100").unwrap();
+        let code = hex::decode("ef00010100040200010004040000000080000160010100").unwrap();
         let container = from_slice(&code).unwrap();
 
-        assert_eq!(container.is_valid_eof().err(), Some(Error::StackUnderflow));
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::StackUnderflow { required: 2, actual: 1 })
+        );
     }
 
     #[test]
     fn stack_overflow() {
-        let code = hex::decode("ef0001010008020002000b0bff0300000000000002010003ff60016001b000016001550050600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050b1").unwrap();
+        let code = hex::decode("ef0001010008020002000e0bff0400000000800003010003ff600160016001e30001600155500050600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001600160016001505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050505050e4").unwrap();
+        let container = from_slice(&code).unwrap();
+
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::StackOverflow)
+        );
+    }
+
+    #[test]
+    fn oversized_declared_heights() {
+        // PUSH0 CALLF 1 POP STOP and PUSH0 JUMPF 1 into a function declaring 0xffff
+        for (code, outputs) in [("5fe300015000", 0), ("5fe50001", EOF_NON_RETURNING_FUNCTION)] {
+            let container = EOFContainer {
+                version: 1,
+                sections: vec![
+                    EOFSection::Type(vec![
+                        EOFTypeSectionEntry { inputs: 0, outputs: 0x80, max_stack_height: 1 },
+                        EOFTypeSectionEntry { inputs: 0, outputs, max_stack_height: 0xffff },
+                    ]),
+                    EOFSection::Code(hex::decode(code).unwrap()),
+                    EOFSection::Code(vec![0xe4]),
+                    EOFSection::Data(vec![].into()),
+                ],
+            };
+            let errors: Vec<Error> = container
+                .validate_all()
+                .into_iter()
+                .map(|diagnostic| diagnostic.error.into_kind())
+                .collect();
+            assert!(errors.contains(&Error::TooLargeMaxStackHeight));
+            assert!(errors.contains(&Error::StackOverflow));
+        }
+    }
+
+    #[test]
+    fn container_too_deep() {
+        // Initcode deploying runtime code that creates the next level: PUSH0 PUSH0 RETURNCODE 0,
+        // and PUSH0 PUSH0 PUSH0 PUSH0 EOFCREATE 0 STOP
+        let initcode = hex::decode("5f5fee00").unwrap();
+        let runtime = hex::decode("5f5f5f5fec0000").unwrap();
+        let mut container = EOFBuilder::new()
+            .add_function(0, EOF_NON_RETURNING_FUNCTION, vec![0x00])
+            .build()
+            .unwrap();
+        for depth in 0..EOF_MAX_CONTAINER_DEPTH {
+            let (code, kind) = if depth % 2 == 0 {
+                (initcode.clone(), ContainerKind::Initcode)
+            } else {
+                (runtime.clone(), ContainerKind::Runtime)
+            };
+            container = EOFBuilder::new()
+                .add_function(0, EOF_NON_RETURNING_FUNCTION, code)
+                .add_subcontainer(container)
+                .set_kind(kind)
+                .build()
+                .unwrap();
+        }
+        let encoded = to_bytes(&container).unwrap();
+        assert_eq!(from_slice(&encoded).unwrap(), container);
+
+        let container = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![
+                    EOFTypeSectionEntry { inputs: 0, outputs: 0x80, max_stack_height: 2 },
+                ]),
+                EOFSection::Code(initcode),
+                EOFSection::Container(container),
+                EOFSection::Data(vec![].into()),
+            ],
+        };
+        assert_eq!(
+            container.is_valid_eof_with_kind(Spec::Osaka, ContainerKind::Initcode),
+            Err(Error::ContainerTooDeep)
+        );
+        let encoded = to_bytes(&container).unwrap();
+        assert_eq!(from_slice(&encoded), Err(Error::ContainerTooDeep));
+    }
+
+    #[test]
+    fn error_locations() {
+        let code = hex::decode("ef00010100040200010004040000000080000160010100").unwrap();
+        let container = from_slice(&code).unwrap();
+        let err = container.is_valid_eof().unwrap_err();
+
+        assert_eq!(
+            err.location(),
+            Some(&Location {
+                section: 0,
+                offset: Some(2),
+                opcode: Some(0x01),
+            })
+        );
+        assert_eq!(
+            err.to_string(),
+            "Stack underflow (required 2, got 1) at code section 0, offset 2 (opcode 0x01)"
+        );
+
+        let code = hex::decode(
+            "ef000101000802000200040003040000000080000000000001e30001006001e4",
+        ).unwrap();
+        let container = from_slice(&code).unwrap();
+        let err = container.is_valid_eof().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid outputs (expected 0, got 1) at code section 1, offset 2 (opcode 0xe4)"
+        );
+
+        let code = hex::decode("ef0001010004020001000304000000008000026001fe").unwrap();
+        let container = from_slice(&code).unwrap();
+        let err = container.is_valid_eof().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid max stack height (declared 2, computed 1) at code section 0"
+        );
+    }
+
+    #[test]
+    fn validate_all() {
+        // Stack underflow in section 0 and a wrong max stack height in section 1
+        let code = hex::decode(
+            "ef000101000802000200040003040000000080000100000002600101005f50e4",
+        ).unwrap();
+        let container = from_slice(&code).unwrap();
+        let diagnostics = container.validate_all();
+
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+        assert_eq!(
+            diagnostics[0].error.kind(),
+            &Error::StackUnderflow { required: 2, actual: 1 }
+        );
+        assert_eq!(diagnostics[1].error.location().map(|l| l.section), Some(1));
+        assert_eq!(
+            diagnostics[1].error.kind(),
+            &Error::InvalidMaxStackHeight { declared: 2, computed: 1 }
+        );
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::StackUnderflow { required: 2, actual: 1 })
+        );
+
+        // Section 1 is never called, which the draft specs only warn about
+        let code = hex::decode("ef00010100080200020001000104000000008000000000000000e4").unwrap();
+        let container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof(),
+            Err(Error::UnreachableCodeSection.at(1, None, None))
+        );
+        assert_eq!(
+            container.validate_all(),
+            vec![Diagnostic {
+                severity: Severity::Error,
+                error: Error::UnreachableCodeSection.at(1, None, None),
+            }]
+        );
+
+        let code = hex::decode("ef00010100080200020001000104000000000000000000000000e4").unwrap();
+        let container = from_slice(&code).unwrap();
+        assert!(container.is_valid_eof_with_spec(Spec::CancunDraft).is_ok());
+        assert_eq!(
+            container.validate_all_with_spec(Spec::CancunDraft)[0].to_string(),
+            "warning: Unreachable code section at code section 1"
+        );
+
+        // Structural problems are collected too
+        let container = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![EOFTypeSectionEntry {
+                    inputs: 0,
+                    outputs: 0x80,
+                    max_stack_height: 0,
+                }]),
+                EOFSection::Code(vec![0x00]),
+                EOFSection::Type(vec![]),
+            ],
+        };
+        assert_eq!(
+            container.validate_all().into_iter().map(|d| d.error).collect::<Vec<_>>(),
+            vec![
+                Error::InvalidSectionOrder,
+                Error::DuplicateTypeSection,
+                Error::MissingDataHeader,
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_all_subcontainers() {
+        let runtime = EOFBuilder::new()
+            .add_function(0, EOF_NON_RETURNING_FUNCTION, vec![0x00])
+            .build()
+            .unwrap();
+        // PUSH0 PUSH0 RETURNCODE 0 | RETF, never called
+        let initcode = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![
+                    EOFTypeSectionEntry { inputs: 0, outputs: 0x80, max_stack_height: 2 },
+                    EOFTypeSectionEntry { inputs: 0, outputs: 0, max_stack_height: 0 },
+                ]),
+                EOFSection::Code(hex::decode("5f5fee00").unwrap()),
+                EOFSection::Code(vec![0xe4]),
+                EOFSection::Container(runtime),
+                EOFSection::Data(vec![].into()),
+            ],
+        };
+        // PUSH0 PUSH0 PUSH0 PUSH0 EOFCREATE 0 POP STOP, with more data than declared
+        let container = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![
+                    EOFTypeSectionEntry { inputs: 0, outputs: 0x80, max_stack_height: 4 },
+                ]),
+                EOFSection::Code(hex::decode("5f5f5f5fec005000").unwrap()),
+                EOFSection::Container(initcode),
+                EOFSection::Data(EOFData { data: vec![0xaa, 0xbb], declared_size: 1 }),
+            ],
+        };
+
+        // The parent's error must not hide the child's unreachable function
+        assert_eq!(
+            container.validate_all(),
+            vec![
+                Diagnostic { severity: Severity::Error, error: Error::InvalidDataSize },
+                Diagnostic {
+                    severity: Severity::Error,
+                    error: Error::UnreachableCodeSection.at(1, None, None),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn orphan_subcontainer() {
+        let runtime = EOFBuilder::new()
+            .add_function(0, EOF_NON_RETURNING_FUNCTION, vec![0x00])
+            .build()
+            .unwrap();
+        let container = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![
+                    EOFTypeSectionEntry { inputs: 0, outputs: 0x80, max_stack_height: 0 },
+                ]),
+                EOFSection::Code(vec![0x00]),
+                EOFSection::Container(runtime),
+                EOFSection::Data(vec![].into()),
+            ],
+        };
+
+        assert_eq!(container.is_valid_eof(), Err(Error::OrphanSubcontainer { index: 0 }));
+    }
+
+    #[test]
+    fn ambiguous_container_kind() {
+        let runtime = EOFBuilder::new()
+            .add_function(0, EOF_NON_RETURNING_FUNCTION, vec![0x00])
+            .build()
+            .unwrap();
+        // PUSH0 PUSH0 PUSH0 PUSH0 EOFCREATE 0 POP PUSH0 PUSH0 RETURNCODE 0
+        let container = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![
+                    EOFTypeSectionEntry { inputs: 0, outputs: 0x80, max_stack_height: 4 },
+                ]),
+                EOFSection::Code(hex::decode("5f5f5f5fec00505f5fee00").unwrap()),
+                EOFSection::Container(runtime),
+                EOFSection::Data(vec![].into()),
+            ],
+        };
+
+        assert_eq!(
+            container.is_valid_eof_with_kind(Spec::Osaka, ContainerKind::Initcode),
+            Err(Error::AmbiguousContainerKind { index: 0 })
+        );
+    }
+
+    #[test]
+    fn incompatible_container_kind() {
+        let with_subcontainer = |code: &str, max_stack_height: u16, subcontainer: Vec<u8>| {
+            EOFContainer {
+                version: 1,
+                sections: vec![
+                    EOFSection::Type(vec![
+                        EOFTypeSectionEntry { inputs: 0, outputs: 0x80, max_stack_height },
+                    ]),
+                    EOFSection::Code(hex::decode(code).unwrap()),
+                    EOFSection::Container(EOFContainer {
+                        version: 1,
+                        sections: vec![
+                            EOFSection::Type(vec![
+                                EOFTypeSectionEntry {
+                                    inputs: 0,
+                                    outputs: 0x80,
+                                    max_stack_height: 2,
+                                },
+                            ]),
+                            EOFSection::Code(subcontainer),
+                            EOFSection::Data(vec![].into()),
+                        ],
+                    }),
+                    EOFSection::Data(vec![].into()),
+                ],
+            }
+        };
+
+        // RETURNCODE in runtime code
+        let container = with_subcontainer("5f5fee00", 2, vec![0x5f, 0x5f, 0x00]);
+        assert_eq!(
+            container.is_valid_eof(),
+            Err(Error::IncompatibleContainerKind.at(0, Some(2), Some(0xee)))
+        );
+        assert!(container.is_valid_eof_with_kind(Spec::Osaka, ContainerKind::Initcode).is_ok());
+
+        // STOP and RETURN in initcode created by EOFCREATE
+        let container = with_subcontainer("5f5f5f5fec0000", 4, vec![0x5f, 0x5f, 0x00]);
+        assert_eq!(
+            container.is_valid_eof(),
+            Err(Error::IncompatibleContainerKind.at(0, Some(2), Some(0x00)))
+        );
+        let container = with_subcontainer("5f5f5f5fec0000", 4, vec![0x5f, 0x5f, 0xf3]);
+        assert_eq!(
+            container.is_valid_eof(),
+            Err(Error::IncompatibleContainerKind.at(0, Some(2), Some(0xf3)))
+        );
+    }
+
+    #[test]
+    fn truncated_data_section() {
+        // Allowed in a subcontainer deployed by RETURNCODE
+        let code = hex::decode(
+            "ef000101000402000100040300010000001604000000008000025f5fee00ef00010100040200010001040004000080000000aabb",
+        ).unwrap();
         let container = from_slice(&code).unwrap();
+        assert!(container.is_valid_eof_with_kind(Spec::Osaka, ContainerKind::Initcode).is_ok());
+
+        // But not at the top level
+        let code = hex::decode("ef00010100040200010001040004000080000000aabb").unwrap();
+        let container = from_slice_relaxed(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::TruncatedDataSection)
+        );
 
-        assert_eq!(container.is_valid_eof().err(), Some(Error::StackOverflow));
+        // Nor in initcode created by EOFCREATE
+        let runtime = EOFBuilder::new()
+            .add_function(0, EOF_NON_RETURNING_FUNCTION, vec![0x00])
+            .build()
+            .unwrap();
+        // PUSH0 PUSH0 RETURNCODE 0
+        let initcode = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![
+                    EOFTypeSectionEntry { inputs: 0, outputs: 0x80, max_stack_height: 2 },
+                ]),
+                EOFSection::Code(hex::decode("5f5fee00").unwrap()),
+                EOFSection::Container(runtime),
+                EOFSection::Data(EOFData { data: vec![0xaa], declared_size: 2 }),
+            ],
+        };
+        // PUSH0 PUSH0 PUSH0 PUSH0 EOFCREATE 0 STOP
+        let container = EOFContainer {
+            version: 1,
+            sections: vec![
+                EOFSection::Type(vec![
+                    EOFTypeSectionEntry { inputs: 0, outputs: 0x80, max_stack_height: 4 },
+                ]),
+                EOFSection::Code(hex::decode("5f5f5f5fec0000").unwrap()),
+                EOFSection::Container(initcode),
+                EOFSection::Data(vec![].into()),
+            ],
+        };
+        assert_eq!(container.is_valid_eof(), Err(Error::TruncatedDataSection));
     }
 
     #[test]
     fn invalid_outputs() {
         let code = hex::decode(
-            "ef000101000802000200040003030000000000000000000001b00001006001b1",
+            "ef000101000802000200040003040000000080000000000001e30001006001e4",
         ).unwrap();
         let container = from_slice(&code).unwrap();
 
-        assert_eq!(container.is_valid_eof().err(), Some(Error::InvalidOutputs));
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidOutputs { expected: 0, actual: 1 })
+        );
     }
 
     #[test]
     fn invalid_max_stack_height() {
-        let code = hex::decode("ef0001010004020001000303000000000000026001fe").unwrap();
+        let code = hex::decode("ef0001010004020001000304000000008000026001fe").unwrap();
         let container = from_slice(&code).unwrap();
 
         assert_eq!(
-            container.is_valid_eof().err(),
-            Some(Error::InvalidMaxStackHeight)
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidMaxStackHeight { declared: 2, computed: 1 })
         );
     }
 
     #[test]
     fn invalid_code_termination() {
-        let code = hex::decode("ef0001010004020001000203000000000000016001").unwrap();
+        let code = hex::decode("ef0001010004020001000204000000008000016001").unwrap();
         let container = from_slice(&code).unwrap();
 
         assert_eq!(
-            container.is_valid_eof().err(),
+            container.is_valid_eof().map_err(Error::into_kind).err(),
             Some(Error::InvalidCodeTermination)
         );
     }
 
     #[test]
     fn unreachable_code() {
-        let code = hex::decode("ef00010100040200010006030000000000000260006000f300").unwrap();
+        let code = hex::decode("ef00010100040200010006040000000080000260006000f300").unwrap();
         let container = from_slice(&code).unwrap();
 
-        assert_eq!(container.is_valid_eof().err(), Some(Error::UnreachableCode));
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::UnreachableCode)
+        );
+    }
+
+    #[test]
+    fn fix_stack_heights() {
+        // Section 1 declares a stale height that would overflow at the CALLF in section 0
+        let code = hex::decode(
+            "ef0001010008020002000b00020400000000800004020103ff5f5f5f5fe300015050500001e4",
+        ).unwrap();
+        let mut container = from_slice(&code).unwrap();
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::StackOverflow)
+        );
+
+        assert_eq!(max_stack_heights(&container), Ok(vec![4, 2]));
+        super::fix_stack_heights(&mut container).unwrap();
+        assert_eq!(container.is_valid_eof(), Ok(()));
+        assert_eq!(
+            container.sections[0],
+            EOFSection::Type(vec![
+                EOFTypeSectionEntry { inputs: 0, outputs: 0x80, max_stack_height: 4 },
+                EOFTypeSectionEntry { inputs: 2, outputs: 1, max_stack_height: 2 },
+            ])
+        );
+    }
+
+    #[test]
+    fn callf_stack_effect() {
+        // The caller must provide the callee's inputs
+        let err = assemble(
+            "
+            .function main
+                push0
+                callf add
+                stop
+            .function add 2 -> 1
+                add
+                retf
+            ",
+        )
+        .unwrap()
+        .is_valid_eof()
+        .unwrap_err();
+        assert_eq!(err.kind(), &Error::StackUnderflow { required: 2, actual: 1 });
+        assert_eq!(err.location().and_then(|l| l.offset), Some(1));
+
+        // Outputs count towards the caller's max stack height
+        let mut container = assemble(
+            "
+            .function main
+                callf three
+                pop
+                pop
+                pop
+                stop
+            .function three 0 -> 3
+                push0
+                push0
+                push0
+                retf
+            ",
+        )
+        .unwrap();
+        assert_eq!(container.is_valid_eof(), Ok(()));
+        if let EOFSection::Type(ref mut types) = container.sections[0] {
+            types[0].max_stack_height = 0;
+        }
+        assert_eq!(
+            container.is_valid_eof().map_err(Error::into_kind).err(),
+            Some(Error::InvalidMaxStackHeight { declared: 0, computed: 3 })
+        );
     }
 }
@@ -0,0 +1,606 @@
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+
+use super::error::{Error, Result};
+use super::types::*;
+
+/// Intermediate representation between the serde data model and `EOFContainer`.
+///
+/// The binary format has a fixed shape, so serialized values are buffered into a tree first and
+/// then matched against it by field and variant names.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    Unit,
+    Int(u64),
+    Bytes(Vec<u8>),
+    Str(String),
+    Seq(Vec<Value>),
+    Map(Vec<(String, Value)>),
+    Variant(String, Box<Value>),
+}
+
+impl Value {
+    fn into_int(self, what: &str) -> Result<u64> {
+        match self {
+            Value::Int(value) => Ok(value),
+            _ => Err(Error::Message(format!("expected an integer for {}", what))),
+        }
+    }
+
+    fn into_bytes(self, what: &str) -> Result<Vec<u8>> {
+        match self {
+            Value::Bytes(bytes) => Ok(bytes),
+            Value::Seq(items) => items
+                .into_iter()
+                .map(|item| {
+                    u8::try_from(item.into_int(what)?)
+                        .map_err(|_| Error::Message(format!("byte out of range in {}", what)))
+                })
+                .collect(),
+            _ => Err(Error::Message(format!("expected bytes for {}", what))),
+        }
+    }
+
+    /// Splits a struct into its fields, or a sequence into positional fields.
+    fn into_fields(self, names: &[&str], what: &str) -> Result<Vec<Value>> {
+        match self {
+            Value::Map(mut entries) => names
+                .iter()
+                .map(|name| {
+                    let index = entries
+                        .iter()
+                        .position(|(key, _)| key == name)
+                        .ok_or_else(|| Error::Message(format!("missing field {} in {}", name, what)))?;
+                    Ok(entries.swap_remove(index).1)
+                })
+                .collect(),
+            Value::Seq(items) if items.len() == names.len() => Ok(items),
+            _ => Err(Error::Message(format!("expected a struct for {}", what))),
+        }
+    }
+
+    pub(crate) fn into_container(self) -> Result<EOFContainer> {
+        let mut fields = self.into_fields(&["version", "sections"], "container")?.into_iter();
+        let (version, sections) = (fields.next().unwrap(), fields.next().unwrap());
+        let version = u8::try_from(version.into_int("version")?)
+            .map_err(|_| Error::UnsupportedVersion)?;
+        let sections = match sections {
+            Value::Seq(sections) => sections,
+            _ => return Err(Error::Message("expected a sequence of sections".to_string())),
+        };
+
+        Ok(EOFContainer {
+            version,
+            sections: sections
+                .into_iter()
+                .map(Value::into_section)
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    fn into_section(self) -> Result<EOFSection> {
+        let (kind, value) = match self {
+            Value::Variant(kind, value) => (kind, *value),
+            _ => return Err(Error::Message("expected a section variant".to_string())),
+        };
+
+        match kind.as_str() {
+            "Code" => Ok(EOFSection::Code(value.into_bytes("code section")?)),
+            "Data" => match value {
+                Value::Map(_) => {
+                    let mut fields = value.into_fields(&["data", "declared_size"], "data")?;
+                    let declared_size = fields.pop().unwrap().into_int("declared_size")?;
+                    Ok(EOFSection::Data(EOFData {
+                        data: fields.pop().unwrap().into_bytes("data section")?,
+                        declared_size: u16::try_from(declared_size)
+                            .map_err(|_| Error::InvalidDataSize)?,
+                    }))
+                }
+                value => Ok(EOFSection::Data(value.into_bytes("data section")?.into())),
+            },
+            "Type" => match value {
+                Value::Seq(entries) => Ok(EOFSection::Type(
+                    entries
+                        .into_iter()
+                        .map(Value::into_type_entry)
+                        .collect::<Result<_>>()?,
+                )),
+                _ => Err(Error::Message("expected a sequence of type entries".to_string())),
+            },
+            "Container" => Ok(EOFSection::Container(value.into_container()?)),
+            _ => Err(Error::UnsupportedSectionKind),
+        }
+    }
+
+    fn into_type_entry(self) -> Result<EOFTypeSectionEntry> {
+        let fields = self.into_fields(&["inputs", "outputs", "max_stack_height"], "type entry")?;
+        let mut fields = fields.into_iter().map(|field| field.into_int("type entry"));
+        let out_of_range = || Error::Message("type entry field out of range".to_string());
+
+        Ok(EOFTypeSectionEntry {
+            inputs: u8::try_from(fields.next().unwrap()?).map_err(|_| out_of_range())?,
+            outputs: u8::try_from(fields.next().unwrap()?).map_err(|_| out_of_range())?,
+            max_stack_height: u16::try_from(fields.next().unwrap()?).map_err(|_| out_of_range())?,
+        })
+    }
+
+    pub(crate) fn from_container(container: EOFContainer) -> Value {
+        let sections = container
+            .sections
+            .into_iter()
+            .map(|section| match section {
+                EOFSection::Code(code) => Value::variant("Code", Value::Bytes(code)),
+                EOFSection::Data(data) if data.is_truncated() => Value::variant(
+                    "Data",
+                    Value::Map(vec![
+                        ("data".to_string(), Value::Bytes(data.data)),
+                        ("declared_size".to_string(), Value::Int(data.declared_size as u64)),
+                    ]),
+                ),
+                EOFSection::Data(data) => Value::variant("Data", Value::Bytes(data.data)),
+                EOFSection::Type(types) => Value::variant(
+                    "Type",
+                    Value::Seq(
+                        types
+                            .into_iter()
+                            .map(|entry| {
+                                Value::Map(vec![
+                                    ("inputs".to_string(), Value::Int(entry.inputs as u64)),
+                                    ("outputs".to_string(), Value::Int(entry.outputs as u64)),
+                                    (
+                                        "max_stack_height".to_string(),
+                                        Value::Int(entry.max_stack_height as u64),
+                                    ),
+                                ])
+                            })
+                            .collect(),
+                    ),
+                ),
+                EOFSection::Container(container) => {
+                    Value::variant("Container", Value::from_container(container))
+                }
+            })
+            .collect();
+
+        Value::Map(vec![
+            ("version".to_string(), Value::Int(container.version as u64)),
+            ("sections".to_string(), Value::Seq(sections)),
+        ])
+    }
+
+    fn variant(name: &str, value: Value) -> Value {
+        Value::Variant(name.to_string(), Box::new(value))
+    }
+}
+
+pub(crate) struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<Value> {
+        Ok(Value::Int(v as u64))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value> {
+        u64::try_from(v)
+            .map(Value::Int)
+            .map_err(|_| Error::Message("negative integers are not supported".to_string()))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value> {
+        Ok(Value::Int(v as u64))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value> {
+        Ok(Value::Int(v as u64))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value> {
+        Ok(Value::Int(v as u64))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value> {
+        Ok(Value::Int(v))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Value> {
+        Err(Error::Message("floats are not supported".to_string()))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Value> {
+        Err(Error::Message("floats are not supported".to_string()))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value> {
+        Ok(Value::Str(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value> {
+        Ok(Value::Str(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value> {
+        Ok(Value::variant(variant, Value::Unit))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value> {
+        Ok(Value::variant(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer> {
+        Ok(SeqSerializer {
+            variant: None,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer> {
+        Ok(SeqSerializer {
+            variant: Some(variant),
+            items: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<MapSerializer> {
+        Ok(MapSerializer {
+            variant: None,
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<MapSerializer> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<MapSerializer> {
+        Ok(MapSerializer {
+            variant: Some(variant),
+            entries: Vec::with_capacity(len),
+            next_key: None,
+        })
+    }
+}
+
+pub(crate) struct SeqSerializer {
+    variant: Option<&'static str>,
+    items: Vec<Value>,
+}
+
+impl SeqSerializer {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.items.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value> {
+        let seq = Value::Seq(self.items);
+        Ok(match self.variant {
+            Some(variant) => Value::variant(variant, seq),
+            None => seq,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+pub(crate) struct MapSerializer {
+    variant: Option<&'static str>,
+    entries: Vec<(String, Value)>,
+    next_key: Option<String>,
+}
+
+impl MapSerializer {
+    fn finish(self) -> Result<Value> {
+        let map = Value::Map(self.entries);
+        Ok(match self.variant {
+            Some(variant) => Value::variant(variant, map),
+            None => map,
+        })
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        self.next_key = Some(match key.serialize(ValueSerializer)? {
+            Value::Str(key) => key,
+            Value::Int(key) => key.to_string(),
+            _ => return Err(Error::Message("map keys must be strings".to_string())),
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| Error::Message("map value without a key".to_string()))?;
+        self.entries.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.entries.push((key.to_string(), value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.entries.push((key.to_string(), value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Value;
+
+    fn into_deserializer(self) -> Value {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Value::Unit => visitor.visit_unit(),
+            Value::Int(value) => visitor.visit_u64(value),
+            Value::Bytes(bytes) => visitor.visit_byte_buf(bytes),
+            Value::Str(value) => visitor.visit_string(value),
+            Value::Seq(items) => visitor.visit_seq(SeqDeserializer::new(items.into_iter())),
+            Value::Map(entries) => visitor.visit_map(MapDeserializer::new(entries.into_iter())),
+            Value::Variant(variant, value) => visitor.visit_enum(VariantDeserializer {
+                variant,
+                value: *value,
+            }),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            // Byte strings also deserialize into `Vec<u8>` and similar sequences.
+            Value::Bytes(bytes) => visitor.visit_seq(SeqDeserializer::new(
+                bytes.into_iter().map(|byte| Value::Int(byte as u64)),
+            )),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Value::Unit => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Value::Str(variant) => visitor.visit_enum(VariantDeserializer {
+                variant,
+                value: Value::Unit,
+            }),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct newtype_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct VariantDeserializer {
+    variant: String,
+    value: Value,
+}
+
+impl<'de> de::EnumAccess<'de> for VariantDeserializer {
+    type Error = Error;
+    type Variant = Value;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Value)> {
+        let variant = seed.deserialize(Value::Str(self.variant))?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Value {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}